@@ -0,0 +1,340 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::components::{Health, Knockback, Movable, Velocity};
+use crate::enemy::{prepare_enemy_projectile, EnemyKind, EnemyMovementSet};
+use crate::flow_field::FlowField;
+use crate::player::Player;
+use crate::resources::AppState::InGame;
+
+pub struct AiPlugin;
+
+impl Plugin for AiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (attach_brains, run_brains.in_set(EnemyMovementSet))
+                .chain()
+                .run_if(in_state(InGame)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AiState {
+    Idle,
+    Chase,
+    WindUp,
+    Dash { direction: Vec2 },
+    Attack,
+    Flee,
+    Cooldown,
+}
+
+#[derive(Clone, Copy)]
+pub enum AiBehavior {
+    Charger {
+        aggro_range: f32,
+        dash_range: f32,
+        wind_up: Duration,
+        dash: Duration,
+        dash_speed: f32,
+        cooldown: Duration,
+    },
+    Kiter {
+        aggro_range: f32,
+        min_range: f32,
+        max_range: f32,
+        fire_interval: Duration,
+        projectile_speed: f32,
+        flee_below_health: f32,
+    },
+}
+
+// everything a brain knows about the world when it is ticked
+pub struct AiContext {
+    pub to_player: Vec2,
+    // the flow field's way around obstacles, none where it doesn't reach
+    pub path_to_player: Option<Vec2>,
+    pub health_fraction: f32,
+    pub move_speed: f32,
+}
+
+#[derive(Default, PartialEq, Debug)]
+pub struct AiOutput {
+    pub velocity: Vec2,
+    pub fire: Option<Vec2>,
+}
+
+#[derive(Component)]
+pub struct AiBrain {
+    pub behavior: AiBehavior,
+    pub state: AiState,
+    time_in_state: Duration,
+}
+
+impl AiBrain {
+    pub fn new(behavior: AiBehavior) -> Self {
+        Self {
+            behavior,
+            state: AiState::Idle,
+            time_in_state: Duration::ZERO,
+        }
+    }
+
+    pub fn tick(&mut self, delta: Duration, context: &AiContext) -> AiOutput {
+        self.time_in_state += delta;
+        if let Some(next) = self.transition(context) {
+            self.state = next;
+            self.time_in_state = Duration::ZERO;
+        }
+        self.act(context)
+    }
+
+    fn transition(&self, context: &AiContext) -> Option<AiState> {
+        let distance = context.to_player.length();
+        match self.behavior {
+            AiBehavior::Charger {
+                aggro_range,
+                dash_range,
+                wind_up,
+                dash,
+                cooldown,
+                ..
+            } => match self.state {
+                AiState::Idle if distance <= aggro_range => Some(AiState::Chase),
+                AiState::Chase if distance <= dash_range => Some(AiState::WindUp),
+                AiState::Chase if distance > aggro_range => Some(AiState::Idle),
+                AiState::WindUp if self.time_in_state >= wind_up => Some(AiState::Dash {
+                    direction: context.to_player.normalize_or_zero(),
+                }),
+                AiState::Dash { .. } if self.time_in_state >= dash => Some(AiState::Cooldown),
+                AiState::Cooldown if self.time_in_state >= cooldown => Some(AiState::Chase),
+                _ => None,
+            },
+            AiBehavior::Kiter {
+                aggro_range,
+                min_range,
+                max_range,
+                fire_interval,
+                flee_below_health,
+                ..
+            } => {
+                let wounded = context.health_fraction < flee_below_health;
+                match self.state {
+                    AiState::Idle if distance <= aggro_range => Some(AiState::Chase),
+                    AiState::Chase if distance > aggro_range => Some(AiState::Idle),
+                    AiState::Chase | AiState::Attack | AiState::Cooldown
+                        if wounded || distance < min_range =>
+                    {
+                        Some(AiState::Flee)
+                    }
+                    AiState::Chase if distance <= max_range => Some(AiState::Attack),
+                    AiState::Attack if distance > max_range => Some(AiState::Chase),
+                    // the shot leaves when attack is entered, then the kiter waits
+                    AiState::Attack => Some(AiState::Cooldown),
+                    AiState::Cooldown if self.time_in_state >= fire_interval => {
+                        Some(AiState::Chase)
+                    }
+                    AiState::Flee if !wounded && distance >= max_range => Some(AiState::Chase),
+                    AiState::Flee if distance > aggro_range => Some(AiState::Idle),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn act(&self, context: &AiContext) -> AiOutput {
+        let towards_player = context.to_player.normalize_or_zero();
+        match self.state {
+            AiState::Idle | AiState::WindUp | AiState::Cooldown => AiOutput::default(),
+            AiState::Chase => AiOutput {
+                velocity: context.path_to_player.unwrap_or(towards_player) * context.move_speed,
+                fire: None,
+            },
+            AiState::Flee => AiOutput {
+                velocity: -towards_player * context.move_speed,
+                fire: None,
+            },
+            AiState::Dash { direction } => {
+                let AiBehavior::Charger { dash_speed, .. } = self.behavior else {
+                    return AiOutput::default();
+                };
+                AiOutput {
+                    velocity: direction * dash_speed,
+                    fire: None,
+                }
+            }
+            AiState::Attack => {
+                let AiBehavior::Kiter {
+                    projectile_speed, ..
+                } = self.behavior
+                else {
+                    return AiOutput::default();
+                };
+                AiOutput {
+                    velocity: Vec2::ZERO,
+                    fire: Some(towards_player * projectile_speed),
+                }
+            }
+        }
+    }
+}
+
+fn attach_brains(mut commands: Commands, q_enemy: Query<(Entity, &EnemyKind), Added<EnemyKind>>) {
+    for (entity, kind) in q_enemy.iter() {
+        if let Some(behavior) = kind.ai_behavior() {
+            commands.entity(entity).insert(AiBrain::new(behavior));
+        }
+    }
+}
+
+fn run_brains(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_brain: Query<
+        (&Transform, &Health, &Movable, &mut Velocity, &mut AiBrain),
+        Without<Knockback>,
+    >,
+    q_player: Query<&Transform, With<Player>>,
+    flow_field: Res<FlowField>,
+) {
+    let player_position = q_player.single().translation.truncate();
+    for (transform, health, movable, mut velocity, mut brain) in q_brain.iter_mut() {
+        let position = transform.translation.truncate();
+        let context = AiContext {
+            to_player: player_position - position,
+            path_to_player: flow_field.direction_at(position),
+            health_fraction: health.current / health.max,
+            move_speed: movable.move_speed,
+        };
+        let output = brain.tick(time.delta(), &context);
+        velocity.0 = output.velocity;
+        if let Some(projectile_velocity) = output.fire {
+            commands.spawn(prepare_enemy_projectile(position, projectile_velocity));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHARGER: AiBehavior = AiBehavior::Charger {
+        aggro_range: 900.,
+        dash_range: 250.,
+        wind_up: Duration::from_millis(600),
+        dash: Duration::from_millis(400),
+        dash_speed: 650.,
+        cooldown: Duration::from_secs(1),
+    };
+    const KITER: AiBehavior = AiBehavior::Kiter {
+        aggro_range: 900.,
+        min_range: 200.,
+        max_range: 400.,
+        fire_interval: Duration::from_millis(1500),
+        projectile_speed: 300.,
+        flee_below_health: 0.5,
+    };
+    const FRAME: Duration = Duration::from_millis(16);
+
+    // the player straight to the right
+    fn context(distance: f32) -> AiContext {
+        AiContext {
+            to_player: Vec2::new(distance, 0.),
+            path_to_player: None,
+            health_fraction: 1.,
+            move_speed: 100.,
+        }
+    }
+
+    #[test]
+    fn charger_winds_up_dashes_and_cools_down() {
+        let mut brain = AiBrain::new(CHARGER);
+        assert_eq!(brain.tick(FRAME, &context(1000.)), AiOutput::default());
+        assert_eq!(brain.state, AiState::Idle);
+
+        let output = brain.tick(FRAME, &context(500.));
+        assert_eq!(brain.state, AiState::Chase);
+        assert_eq!(output.velocity, Vec2::new(100., 0.));
+
+        brain.tick(FRAME, &context(200.));
+        assert_eq!(brain.state, AiState::WindUp);
+        assert_eq!(
+            brain
+                .tick(Duration::from_millis(500), &context(200.))
+                .velocity,
+            Vec2::ZERO
+        );
+        assert_eq!(brain.state, AiState::WindUp);
+
+        let output = brain.tick(Duration::from_millis(100), &context(200.));
+        assert_eq!(brain.state, AiState::Dash { direction: Vec2::X });
+        assert_eq!(output.velocity, Vec2::new(650., 0.));
+        // the dash is committed, the player stepping aside doesn't turn it
+        let mut dodged = context(200.);
+        dodged.to_player = Vec2::new(0., 200.);
+        assert_eq!(brain.tick(FRAME, &dodged).velocity, Vec2::new(650., 0.));
+
+        brain.tick(Duration::from_millis(400), &dodged);
+        assert_eq!(brain.state, AiState::Cooldown);
+        brain.tick(Duration::from_secs(1), &dodged);
+        assert_eq!(brain.state, AiState::Chase);
+    }
+
+    #[test]
+    fn chase_follows_the_flow_field() {
+        let mut brain = AiBrain::new(CHARGER);
+        let mut around_a_rock = context(500.);
+        around_a_rock.path_to_player = Some(Vec2::Y);
+        assert_eq!(
+            brain.tick(FRAME, &around_a_rock).velocity,
+            Vec2::new(0., 100.)
+        );
+    }
+
+    #[test]
+    fn kiter_keeps_its_distance_and_fires() {
+        let mut brain = AiBrain::new(KITER);
+        brain.tick(FRAME, &context(600.));
+        assert_eq!(brain.state, AiState::Chase);
+
+        let output = brain.tick(FRAME, &context(300.));
+        assert_eq!(brain.state, AiState::Attack);
+        assert_eq!(output.velocity, Vec2::ZERO);
+        assert_eq!(output.fire, Some(Vec2::new(300., 0.)));
+
+        let output = brain.tick(FRAME, &context(300.));
+        assert_eq!(brain.state, AiState::Cooldown);
+        assert_eq!(output.fire, None);
+        brain.tick(Duration::from_millis(1500), &context(300.));
+        assert_eq!(brain.state, AiState::Chase);
+
+        // too close, it backs off until it's out at max range again
+        let output = brain.tick(FRAME, &context(100.));
+        assert_eq!(brain.state, AiState::Flee);
+        assert_eq!(output.velocity, Vec2::new(-100., 0.));
+        brain.tick(FRAME, &context(300.));
+        assert_eq!(brain.state, AiState::Flee);
+        brain.tick(FRAME, &context(450.));
+        assert_eq!(brain.state, AiState::Chase);
+    }
+
+    #[test]
+    fn wounded_kiter_flees_until_out_of_range() {
+        let mut brain = AiBrain::new(KITER);
+        brain.tick(FRAME, &context(600.));
+        let mut wounded = context(300.);
+        wounded.health_fraction = 0.3;
+        brain.tick(FRAME, &wounded);
+        assert_eq!(brain.state, AiState::Flee);
+
+        wounded.to_player = Vec2::new(500., 0.);
+        brain.tick(FRAME, &wounded);
+        assert_eq!(brain.state, AiState::Flee);
+        wounded.to_player = Vec2::new(1000., 0.);
+        brain.tick(FRAME, &wounded);
+        assert_eq!(brain.state, AiState::Idle);
+    }
+}
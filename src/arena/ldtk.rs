@@ -0,0 +1,286 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::TileFlip;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::arena::{RawLayer, RawMap, RawObject, RawTile};
+use crate::data::{asset_path, read_asset, relative_asset_path};
+
+// hazards say which kind they are through a string field of this name
+const HAZARD_KIND_FIELD: &str = "kind";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Project {
+    #[serde(default)]
+    external_levels: bool,
+    default_grid_size: u32,
+    defs: Definitions,
+    levels: Vec<Level>,
+}
+
+#[derive(Deserialize)]
+struct Definitions {
+    tilesets: Vec<TilesetDefinition>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TilesetDefinition {
+    uid: i64,
+    identifier: String,
+    rel_path: Option<String>,
+    tile_grid_size: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    padding: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Level {
+    identifier: String,
+    px_wid: u32,
+    px_hei: u32,
+    layer_instances: Option<Vec<LayerInstance>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LayerInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__gridSize")]
+    grid_size: u32,
+    #[serde(rename = "__tilesetDefUid")]
+    tileset_def_uid: Option<i64>,
+    #[serde(default)]
+    grid_tiles: Vec<TileInstance>,
+    #[serde(default)]
+    auto_layer_tiles: Vec<TileInstance>,
+    #[serde(default)]
+    entity_instances: Vec<EntityInstance>,
+}
+
+#[derive(Deserialize)]
+struct TileInstance {
+    // top left corner in the layer
+    px: [u32; 2],
+    t: u32,
+    // bit 0 flips x, bit 1 flips y
+    f: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntityInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__pivot")]
+    pivot: [f32; 2],
+    px: [f32; 2],
+    width: f32,
+    height: f32,
+    #[serde(default)]
+    field_instances: Vec<FieldInstance>,
+}
+
+#[derive(Deserialize)]
+struct FieldInstance {
+    #[serde(rename = "__identifier")]
+    identifier: String,
+    #[serde(rename = "__value")]
+    value: Value,
+}
+
+pub(super) fn load(path: &str, level: Option<&str>) -> Result<RawMap, String> {
+    parse(path, &read_asset(path)?, level)
+}
+
+fn parse(path: &str, source: &str, level: Option<&str>) -> Result<RawMap, String> {
+    let project: Project = serde_json::from_str(source)
+        .map_err(|err| format!("could not parse the project: {err}"))?;
+    if project.external_levels {
+        return Err(
+            "levels are saved in separate files, turn off that project setting".to_string(),
+        );
+    }
+    let level = match level {
+        Some(name) => project
+            .levels
+            .iter()
+            .find(|level| level.identifier == name)
+            .ok_or_else(|| format!("there is no level `{name}`"))?,
+        None => project.levels.first().ok_or("the project has no levels")?,
+    };
+    let grid = project.default_grid_size;
+    let mut raw = RawMap {
+        tile_size: UVec2::splat(grid),
+        size: UVec2::new(level.px_wid, level.px_hei) / grid,
+        layers: Vec::new(),
+        objects: Vec::new(),
+    };
+
+    // ldtk lists layers from the top down
+    let layers = level.layer_instances.iter().flatten().rev();
+    for layer in layers {
+        let name = &layer.identifier;
+        if layer.grid_size != grid {
+            return Err(format!(
+                "layer `{name}` uses a {}px grid, the project uses {grid}px",
+                layer.grid_size
+            ));
+        }
+        raw.objects
+            .extend(layer.entity_instances.iter().map(|entity| {
+                let size = Vec2::new(entity.width, entity.height);
+                RawObject {
+                    kind: entity.identifier.clone(),
+                    name: hazard_kind(entity),
+                    position: Vec2::from(entity.px) - Vec2::from(entity.pivot) * size,
+                    size,
+                }
+            }));
+
+        let tiles: Vec<&TileInstance> = layer
+            .grid_tiles
+            .iter()
+            .chain(layer.auto_layer_tiles.iter())
+            .collect();
+        if tiles.is_empty() {
+            continue;
+        }
+        let tileset = layer
+            .tileset_def_uid
+            .and_then(|uid| {
+                project
+                    .defs
+                    .tilesets
+                    .iter()
+                    .find(|tileset| tileset.uid == uid)
+            })
+            .ok_or_else(|| format!("layer `{name}` has tiles but no tileset"))?;
+        raw.layers.push(RawLayer {
+            image: tileset_image(path, tileset)?,
+            tile_size: Vec2::splat(tileset.tile_grid_size as f32),
+            spacing: Vec2::splat(tileset.spacing as f32),
+            tiles: tiles
+                .into_iter()
+                .map(|tile| RawTile {
+                    cell: UVec2::from(tile.px) / grid,
+                    index: tile.t,
+                    flip: TileFlip {
+                        x: tile.f & 1 != 0,
+                        y: tile.f & 2 != 0,
+                        d: false,
+                    },
+                })
+                .collect(),
+        });
+    }
+    Ok(raw)
+}
+
+fn tileset_image(project_path: &str, tileset: &TilesetDefinition) -> Result<String, String> {
+    let name = &tileset.identifier;
+    // the tilemap renderer has spacing between tiles but nothing around them
+    if tileset.padding != 0 {
+        return Err(format!("tileset `{name}` has padding, set it to 0"));
+    }
+    let Some(rel_path) = &tileset.rel_path else {
+        return Err(format!("tileset `{name}` has no image"));
+    };
+    let image = relative_asset_path(project_path, rel_path);
+    if !asset_path(&image).exists() {
+        return Err(format!(
+            "image `{rel_path}` of tileset `{name}` does not exist, looked for {image}"
+        ));
+    }
+    Ok(image)
+}
+
+fn hazard_kind(entity: &EntityInstance) -> String {
+    entity
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == HAZARD_KIND_FIELD)
+        .and_then(|field| field.value.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // layers come top down, like ldtk saves them
+    fn project(tileset_path: &str, layers: &str) -> String {
+        format!(
+            r#"{{
+                "defaultGridSize": 32,
+                "defs": {{"tilesets": [{{"uid": 7, "identifier": "Terrain",
+                    "relPath": "{tileset_path}", "tileGridSize": 32}}]}},
+                "levels": [{{"identifier": "Arena", "pxWid": 64, "pxHei": 64,
+                    "layerInstances": [{layers}]}}]
+            }}"#
+        )
+    }
+
+    fn tile_layer(name: &str, index: u32) -> String {
+        format!(
+            r#"{{"__identifier": "{name}", "__gridSize": 32, "__tilesetDefUid": 7,
+                "gridTiles": [{{"px": [32, 0], "t": {index}, "f": 1}}]}}"#
+        )
+    }
+
+    const ENTITIES: &str = r#"{"__identifier": "Entities", "__gridSize": 32, "entityInstances": [
+        {"__identifier": "PlayerSpawn", "__pivot": [0.5, 1.0], "px": [32, 64], "width": 32, "height": 32},
+        {"__identifier": "Hazard", "__pivot": [0.0, 0.0], "px": [0, 0], "width": 64, "height": 32,
+            "fieldInstances": [{"__identifier": "kind", "__value": "lava"}]}
+    ]}"#;
+
+    #[test]
+    fn reads_layers_bottom_up_and_entities_by_their_pivot() {
+        let layers = format!(
+            "{ENTITIES}, {}, {}",
+            tile_layer("Decor", 3),
+            tile_layer("Ground", 0)
+        );
+        let source = project("../textures/terrain_tiles.png", &layers);
+        let raw = parse("arenas/test.ldtk", &source, None).unwrap();
+
+        let indices: Vec<u32> = raw
+            .layers
+            .iter()
+            .map(|layer| layer.tiles[0].index)
+            .collect();
+        assert_eq!(indices, vec![0, 3]);
+        assert_eq!(raw.layers[0].image, "textures/terrain_tiles.png");
+        assert_eq!(raw.layers[0].tiles[0].cell, UVec2::new(1, 0));
+        assert!(raw.layers[0].tiles[0].flip.x);
+
+        // pivoted on its bottom middle, so the top left corner is half a tile left and a tile up
+        assert_eq!(raw.objects[0].kind, "PlayerSpawn");
+        assert_eq!(raw.objects[0].position, Vec2::new(16., 32.));
+        assert_eq!(raw.objects[0].center(), Vec2::new(32., 48.));
+        assert_eq!(raw.objects[1].name, "lava");
+        assert_eq!(raw.objects[1].position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn rejects_missing_levels_and_tileset_images() {
+        let source = project("../textures/terrain_tiles.png", ENTITIES);
+        let err = parse("arenas/test.ldtk", &source, Some("Cave"))
+            .err()
+            .unwrap();
+        assert!(err.contains("there is no level `Cave`"), "{err}");
+
+        let source = project("../textures/missing.png", &tile_layer("Ground", 0));
+        let err = parse("arenas/test.ldtk", &source, None).err().unwrap();
+        assert!(
+            err.contains("image `../textures/missing.png` of tileset `Terrain` does not exist"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,425 @@
+mod ldtk;
+mod tiled;
+
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ecs_tilemap::prelude::*;
+use serde::Deserialize;
+
+use crate::bounds::{ArenaBounds, BoundsShape};
+use crate::data::asset_path;
+use crate::hazard::spawn_area_effect;
+use crate::map::{tile_to_world_pos, world_pos_to_tile, TileOccupancy, TILE_SIZE};
+use crate::resources::AppState::InGame;
+use crate::terrain::HazardKind;
+
+// same depth as the procedural chunks, every further layer draws slightly above
+const ARENA_Z: f32 = -10.0;
+const LAYER_Z_STEP: f32 = 0.1;
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(InGame),
+            spawn_arena.run_if(resource_exists::<Arena>().and_then(run_once())),
+        );
+    }
+}
+
+// a hand made map in the assets folder, either Tiled (.tmx/.tmj) or LDtk (.ldtk)
+#[derive(Deserialize, Clone, Debug)]
+pub struct ArenaSource {
+    pub path: String,
+    // LDtk projects hold several levels, the first one is used if none is named
+    #[serde(default)]
+    pub level: Option<String>,
+}
+
+// what the importers produce, in map pixels with y pointing down like in both editors
+struct RawMap {
+    tile_size: UVec2,
+    size: UVec2,
+    // bottom to top, one per tileset used by an editor layer
+    layers: Vec<RawLayer>,
+    objects: Vec<RawObject>,
+}
+
+struct RawLayer {
+    image: String,
+    tile_size: Vec2,
+    spacing: Vec2,
+    tiles: Vec<RawTile>,
+}
+
+struct RawTile {
+    // column and row from the top left corner
+    cell: UVec2,
+    index: u32,
+    flip: TileFlip,
+}
+
+struct RawObject {
+    kind: String,
+    name: String,
+    // top left corner, points have no size
+    position: Vec2,
+    size: Vec2,
+}
+
+impl RawObject {
+    fn center(&self) -> Vec2 {
+        self.position + self.size / 2.
+    }
+
+    fn has_area(&self) -> bool {
+        self.size.x > 0. && self.size.y > 0.
+    }
+}
+
+struct ArenaLayer {
+    image: String,
+    tile_size: Vec2,
+    spacing: Vec2,
+    tiles: Vec<(TilePos, TileTextureIndex, TileFlip)>,
+}
+
+pub struct ArenaHazard {
+    pub kind: HazardKind,
+    pub area: Rect,
+}
+
+// placed so the player spawn sits on the world origin, where every run starts
+#[derive(Resource)]
+pub struct Arena {
+    pub name: String,
+    // world tile of the bottom left corner
+    pub min_tile: IVec2,
+    pub size: UVec2,
+    layers: Vec<ArenaLayer>,
+    pub rift_spawns: Vec<Vec2>,
+    pub obstacles: HashSet<IVec2>,
+    pub hazards: Vec<ArenaHazard>,
+}
+
+impl Arena {
+    pub fn load(source: &ArenaSource) -> Result<Self, String> {
+        let path = &source.path;
+        if !asset_path(path).exists() {
+            return Err(format!("arena `{path}` does not exist"));
+        }
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        if source.level.is_some() && extension != "ldtk" {
+            return Err(format!("arena `{path}`: only LDtk maps have levels"));
+        }
+        let raw = match extension {
+            "tmx" => tiled::load_tmx(path),
+            "tmj" | "json" => tiled::load_tmj(path),
+            "ldtk" => ldtk::load(path, source.level.as_deref()),
+            _ => Err(format!("`.{extension}` is not a Tiled or LDtk map")),
+        };
+        raw.and_then(|raw| Self::from_raw(path, raw))
+            .map_err(|err| format!("arena `{path}`: {err}"))
+    }
+
+    fn from_raw(path: &str, raw: RawMap) -> Result<Self, String> {
+        let grid = UVec2::new(TILE_SIZE.x as u32, TILE_SIZE.y as u32);
+        if raw.tile_size != grid {
+            return Err(format!(
+                "tiles are {}x{} pixels, arenas use {}x{}",
+                raw.tile_size.x, raw.tile_size.y, grid.x, grid.y
+            ));
+        }
+        if raw.size.x == 0 || raw.size.y == 0 {
+            return Err("the map has no tiles".to_string());
+        }
+        let tile_size = grid.as_vec2();
+        let map_size = raw.size.as_vec2() * tile_size;
+        let in_map = |point: Vec2| Rect::from_corners(Vec2::ZERO, map_size).contains(point);
+
+        let mut player_spawn = None;
+        for object in raw
+            .objects
+            .iter()
+            .filter(|object| object.kind == "PlayerSpawn")
+        {
+            if player_spawn.replace(object.center()).is_some() {
+                return Err("there is more than one PlayerSpawn".to_string());
+            }
+            if !in_map(object.center()) {
+                return Err("the PlayerSpawn is outside the map".to_string());
+            }
+        }
+        // flip y and snap the spawn tile onto the world origin
+        let flip = |point: Vec2| Vec2::new(point.x, map_size.y - point.y) - tile_size / 2.;
+        let spawn_tile = world_pos_to_tile(flip(player_spawn.unwrap_or(map_size / 2.)));
+        let to_world = |point: Vec2| flip(point) - tile_to_world_pos(spawn_tile);
+        let to_world_rect = |object: &RawObject| {
+            Rect::from_corners(
+                to_world(object.position),
+                to_world(object.position + object.size),
+            )
+        };
+
+        let mut rift_spawns = Vec::new();
+        let mut obstacles = HashSet::new();
+        let mut hazards = Vec::new();
+        for object in &raw.objects {
+            let at = object.center();
+            match object.kind.as_str() {
+                "PlayerSpawn" => {}
+                "RiftSpawn" if !in_map(at) => {
+                    return Err(format!(
+                        "RiftSpawn at {}, {} is outside the map",
+                        at.x, at.y
+                    ));
+                }
+                "RiftSpawn" => rift_spawns.push(to_world(at)),
+                "Obstacle" | "Hazard" if !object.has_area() => {
+                    return Err(format!("{} at {}, {} has no area", object.kind, at.x, at.y));
+                }
+                "Obstacle" => {
+                    // the same edge rule as TileOccupancy::overlaps
+                    let area = to_world_rect(object).inset(-0.01);
+                    let min = world_pos_to_tile(area.min);
+                    let max = world_pos_to_tile(area.max);
+                    for y in min.y..=max.y {
+                        for x in min.x..=max.x {
+                            obstacles.insert(IVec2::new(x, y));
+                        }
+                    }
+                }
+                "Hazard" if object.name.is_empty() => {
+                    return Err(format!(
+                        "Hazard at {}, {} has no kind, set its name in Tiled or its `kind` field in LDtk",
+                        at.x, at.y
+                    ));
+                }
+                "Hazard" => {
+                    let kind = HazardKind::from_name(&object.name).ok_or_else(|| {
+                        format!(
+                            "Hazard at {}, {} is `{}`, which isn't lava, mud, ice or spring",
+                            at.x, at.y, object.name
+                        )
+                    })?;
+                    hazards.push(ArenaHazard {
+                        kind,
+                        area: to_world_rect(object),
+                    });
+                }
+                other => warn!("arena `{path}`: ignoring object of unknown type `{other}`"),
+            }
+        }
+
+        let mut layers = Vec::with_capacity(raw.layers.len());
+        for layer in raw.layers {
+            let mut tiles = Vec::with_capacity(layer.tiles.len());
+            for tile in layer.tiles {
+                if tile.cell.cmpge(raw.size).any() {
+                    return Err(format!(
+                        "a tile of `{}` is outside the map, at column {} row {}",
+                        layer.image, tile.cell.x, tile.cell.y
+                    ));
+                }
+                let position = TilePos {
+                    x: tile.cell.x,
+                    y: raw.size.y - 1 - tile.cell.y,
+                };
+                tiles.push((position, TileTextureIndex(tile.index), tile.flip));
+            }
+            layers.push(ArenaLayer {
+                image: layer.image,
+                tile_size: layer.tile_size,
+                spacing: layer.spacing,
+                tiles,
+            });
+        }
+
+        Ok(Self {
+            name: path.to_string(),
+            min_tile: -spawn_tile,
+            size: raw.size,
+            layers,
+            rift_spawns,
+            obstacles,
+            hazards,
+        })
+    }
+
+    // the whole map, arenas are finite even without bounds in their stage
+    pub fn bounds(&self) -> ArenaBounds {
+        let tile_size = Vec2::new(TILE_SIZE.x, TILE_SIZE.y);
+        let size = self.size.as_vec2() * tile_size;
+        let min = tile_to_world_pos(self.min_tile) - tile_size / 2.;
+        ArenaBounds {
+            shape: BoundsShape::Rectangle {
+                width: size.x,
+                height: size.y,
+            },
+            center: min + size / 2.,
+        }
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = IVec2> + '_ {
+        let size = self.size.as_ivec2();
+        (0..size.y).flat_map(move |y| (0..size.x).map(move |x| self.min_tile + IVec2::new(x, y)))
+    }
+}
+
+fn spawn_arena(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    asset_server: Res<AssetServer>,
+    mut occupancy: ResMut<TileOccupancy>,
+) {
+    info!(
+        "building arena `{}`, {} rift spawns, {} hazards",
+        arena.name,
+        arena.rift_spawns.len(),
+        arena.hazards.len()
+    );
+
+    let size = TilemapSize {
+        x: arena.size.x,
+        y: arena.size.y,
+    };
+    for (index, layer) in arena.layers.iter().enumerate() {
+        let tilemap_entity = commands.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(size);
+        for (position, texture_index, flip) in &layer.tiles {
+            let tile_entity = commands
+                .spawn(TileBundle {
+                    position: *position,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    texture_index: *texture_index,
+                    flip: *flip,
+                    ..default()
+                })
+                .id();
+            commands.entity(tilemap_entity).add_child(tile_entity);
+            tile_storage.set(position, tile_entity);
+        }
+
+        let z = ARENA_Z + index as f32 * LAYER_Z_STEP;
+        commands.entity(tilemap_entity).insert(TilemapBundle {
+            grid_size: TILE_SIZE.into(),
+            size,
+            storage: tile_storage,
+            texture: TilemapTexture::Single(asset_server.load(layer.image.clone())),
+            tile_size: TilemapTileSize {
+                x: layer.tile_size.x,
+                y: layer.tile_size.y,
+            },
+            spacing: TilemapSpacing {
+                x: layer.spacing.x,
+                y: layer.spacing.y,
+            },
+            transform: Transform::from_translation(tile_to_world_pos(arena.min_tile).extend(z)),
+            ..default()
+        });
+    }
+
+    for tile in &arena.obstacles {
+        occupancy.block(*tile);
+    }
+    for hazard in &arena.hazards {
+        spawn_area_effect(&mut commands, hazard.kind, hazard.area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(kind: &str, position: Vec2, size: Vec2) -> RawObject {
+        RawObject {
+            kind: kind.to_string(),
+            name: String::new(),
+            position,
+            size,
+        }
+    }
+
+    // 10 by 6 tiles, the player spawns in the middle of column 3, row 2 from the top
+    fn map(objects: Vec<RawObject>) -> RawMap {
+        let tile = |x, y| RawTile {
+            cell: UVec2::new(x, y),
+            index: 1,
+            flip: TileFlip::default(),
+        };
+        let mut all = vec![object("PlayerSpawn", Vec2::new(112., 80.), Vec2::ZERO)];
+        all.extend(objects);
+        RawMap {
+            tile_size: UVec2::splat(32),
+            size: UVec2::new(10, 6),
+            layers: vec![RawLayer {
+                image: "textures/terrain_tiles.png".to_string(),
+                tile_size: Vec2::splat(32.),
+                spacing: Vec2::ZERO,
+                tiles: vec![tile(3, 2), tile(0, 0)],
+            }],
+            objects: all,
+        }
+    }
+
+    #[test]
+    fn player_spawn_lands_on_the_origin() {
+        let arena = Arena::from_raw("test", map(vec![])).unwrap();
+        // row 2 of 6 from the top is row 3 from the bottom
+        assert_eq!(arena.min_tile, IVec2::new(-3, -3));
+        assert_eq!(arena.layers[0].tiles[0].0, TilePos { x: 3, y: 3 });
+        assert_eq!(arena.layers[0].tiles[1].0, TilePos { x: 0, y: 5 });
+        assert_eq!(arena.bounds().center, Vec2::new(48., -16.));
+    }
+
+    #[test]
+    fn objects_are_flipped_into_world_tiles() {
+        let arena = Arena::from_raw(
+            "test",
+            map(vec![
+                // top right corner
+                object("RiftSpawn", Vec2::new(304., 16.), Vec2::ZERO),
+                // the two leftmost tiles of the top row
+                object("Obstacle", Vec2::ZERO, Vec2::new(64., 32.)),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(arena.rift_spawns, vec![tile_to_world_pos(IVec2::new(6, 2))]);
+        let obstacles: HashSet<IVec2> = [IVec2::new(-3, 2), IVec2::new(-2, 2)].into();
+        assert_eq!(arena.obstacles, obstacles);
+    }
+
+    #[test]
+    fn rejects_misplaced_spawns() {
+        let outside = map(vec![object("RiftSpawn", Vec2::new(400., 20.), Vec2::ZERO)]);
+        let err = Arena::from_raw("test", outside).err().unwrap();
+        assert!(
+            err.contains("RiftSpawn at 400, 20 is outside the map"),
+            "{err}"
+        );
+
+        let second = map(vec![object("PlayerSpawn", Vec2::new(16., 16.), Vec2::ZERO)]);
+        let err = Arena::from_raw("test", second).err().unwrap();
+        assert!(err.contains("more than one PlayerSpawn"), "{err}");
+    }
+
+    #[test]
+    fn loads_the_sample_arena() {
+        let source = ArenaSource {
+            path: "arenas/sample.tmj".to_string(),
+            level: None,
+        };
+        let arena = Arena::load(&source).unwrap();
+        assert_eq!(arena.size, UVec2::new(24, 16));
+        assert_eq!(arena.min_tile, IVec2::new(-12, -7));
+        assert_eq!(arena.rift_spawns.len(), 4);
+        assert_eq!(arena.hazards.len(), 1);
+        assert!(!arena.obstacles.is_empty() && !arena.obstacles.contains(&IVec2::ZERO));
+    }
+}
@@ -0,0 +1,684 @@
+use std::path::Path;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_ecs_tilemap::prelude::TileFlip;
+use serde::{de::IgnoredAny, Deserialize};
+
+use crate::arena::{RawLayer, RawMap, RawObject, RawTile};
+use crate::data::{asset_path, read_asset, relative_asset_path};
+
+// the top bits of a gid flip the tile, the last one only matters for hexagonal maps
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+
+// checked before the rest, infinite maps keep their tiles in chunks instead of layer data
+#[derive(Deserialize)]
+struct Header {
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+}
+
+// the json layout, the xml files are converted into it
+#[derive(Deserialize)]
+struct TiledMap {
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    tilesets: Vec<TilesetRef>,
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TilesetRef {
+    External {
+        firstgid: u32,
+        source: String,
+    },
+    Embedded {
+        firstgid: u32,
+        #[serde(flatten)]
+        tileset: Tileset,
+    },
+}
+
+#[derive(Deserialize)]
+struct Tileset {
+    name: String,
+    image: String,
+    tilewidth: u32,
+    tileheight: u32,
+    tilecount: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    margin: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum TiledLayer {
+    TileLayer {
+        name: String,
+        data: LayerData,
+    },
+    ObjectGroup {
+        #[serde(default)]
+        objects: Vec<TiledObject>,
+    },
+    Group {
+        #[serde(default)]
+        layers: Vec<TiledLayer>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LayerData {
+    Csv(Vec<u32>),
+    // base64, possibly compressed
+    Encoded(IgnoredAny),
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    name: String,
+    // called class in tiled 1.9, type before and after
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    // tile objects hang from their bottom left corner
+    #[serde(default)]
+    gid: Option<u32>,
+}
+
+// a tileset with its image checked and its path made relative to the assets folder
+struct LoadedTileset {
+    firstgid: u32,
+    image: String,
+    tile_size: Vec2,
+    spacing: Vec2,
+    tilecount: u32,
+}
+
+pub(super) fn load_tmj(path: &str) -> Result<RawMap, String> {
+    parse_tmj(path, &read_asset(path)?)
+}
+
+pub(super) fn load_tmx(path: &str) -> Result<RawMap, String> {
+    parse_tmx(path, &read_asset(path)?)
+}
+
+fn parse_tmj(path: &str, source: &str) -> Result<RawMap, String> {
+    let parse_error = |err: serde_json::Error| format!("could not parse the map: {err}");
+    check_header(serde_json::from_str(source).map_err(parse_error)?)?;
+    convert(path, serde_json::from_str(source).map_err(parse_error)?)
+}
+
+fn parse_tmx(path: &str, source: &str) -> Result<RawMap, String> {
+    let parse_error = |err: quick_xml::DeError| format!("could not parse the map: {err}");
+    let header: xml::Header = quick_xml::de::from_str(source).map_err(parse_error)?;
+    check_header(header.into_tiled())?;
+    let map: xml::Map = quick_xml::de::from_str(source).map_err(parse_error)?;
+    convert(path, map.into_tiled()?)
+}
+
+fn check_header(header: Header) -> Result<(), String> {
+    if header.orientation != "orthogonal" {
+        return Err(format!(
+            "{} maps are not supported, only orthogonal ones",
+            header.orientation
+        ));
+    }
+    if header.infinite {
+        return Err(
+            "infinite maps are not supported, turn off Infinite in the map properties".to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn convert(path: &str, map: TiledMap) -> Result<RawMap, String> {
+    let mut tilesets = map
+        .tilesets
+        .into_iter()
+        .map(|tileset| load_tileset(path, tileset))
+        .collect::<Result<Vec<_>, _>>()?;
+    tilesets.sort_by_key(|tileset| tileset.firstgid);
+
+    let mut raw = RawMap {
+        tile_size: UVec2::new(map.tilewidth, map.tileheight),
+        size: UVec2::new(map.width, map.height),
+        layers: Vec::new(),
+        objects: Vec::new(),
+    };
+    add_layers(&mut raw, &tilesets, map.layers)?;
+    Ok(raw)
+}
+
+fn load_tileset(map_path: &str, tileset: TilesetRef) -> Result<LoadedTileset, String> {
+    let (firstgid, tileset, tileset_path) = match tileset {
+        TilesetRef::Embedded { firstgid, tileset } => (firstgid, tileset, map_path.to_string()),
+        TilesetRef::External { firstgid, source } => {
+            let tileset_path = relative_asset_path(map_path, &source);
+            if !asset_path(&tileset_path).exists() {
+                return Err(format!(
+                    "tileset `{source}` does not exist, looked for {tileset_path}"
+                ));
+            }
+            let file = read_asset(&tileset_path)?;
+            let tileset = match Path::new(&source).extension().and_then(|ext| ext.to_str()) {
+                Some("tsx") => quick_xml::de::from_str::<xml::Tileset>(&file)
+                    .map_err(|err| err.to_string())
+                    .and_then(|tileset| tileset.to_tiled()),
+                _ => serde_json::from_str::<Tileset>(&file).map_err(|err| err.to_string()),
+            }
+            .map_err(|err| format!("could not parse tileset `{source}`: {err}"))?;
+            (firstgid, tileset, tileset_path)
+        }
+    };
+
+    let name = &tileset.name;
+    // the tilemap renderer has spacing between tiles but nothing around them
+    if tileset.margin != 0 {
+        return Err(format!("tileset `{name}` has a margin, set it to 0"));
+    }
+    let image = relative_asset_path(&tileset_path, &tileset.image);
+    if !asset_path(&image).exists() {
+        return Err(format!(
+            "image `{}` of tileset `{name}` does not exist, looked for {image}",
+            tileset.image
+        ));
+    }
+    Ok(LoadedTileset {
+        firstgid,
+        image,
+        tile_size: UVec2::new(tileset.tilewidth, tileset.tileheight).as_vec2(),
+        spacing: Vec2::splat(tileset.spacing as f32),
+        tilecount: tileset.tilecount,
+    })
+}
+
+fn add_layers(
+    raw: &mut RawMap,
+    tilesets: &[LoadedTileset],
+    layers: Vec<TiledLayer>,
+) -> Result<(), String> {
+    for layer in layers {
+        match layer {
+            TiledLayer::TileLayer { name, data } => {
+                let LayerData::Csv(data) = data else {
+                    return Err(format!(
+                        "layer `{name}` is base64 encoded, save the map with the CSV layer format"
+                    ));
+                };
+                add_tile_layer(raw, tilesets, &name, &data)?;
+            }
+            TiledLayer::ObjectGroup { objects } => {
+                raw.objects.extend(objects.into_iter().map(|object| {
+                    let size = Vec2::new(object.width, object.height);
+                    let mut position = Vec2::new(object.x, object.y);
+                    if object.gid.is_some() {
+                        position.y -= size.y;
+                    }
+                    RawObject {
+                        kind: if object.class.is_empty() {
+                            object.kind
+                        } else {
+                            object.class
+                        },
+                        name: object.name,
+                        position,
+                        size,
+                    }
+                }));
+            }
+            TiledLayer::Group { layers } => add_layers(raw, tilesets, layers)?,
+            TiledLayer::Other => {}
+        }
+    }
+    Ok(())
+}
+
+fn add_tile_layer(
+    raw: &mut RawMap,
+    tilesets: &[LoadedTileset],
+    name: &str,
+    data: &[u32],
+) -> Result<(), String> {
+    let expected = (raw.size.x * raw.size.y) as usize;
+    if data.len() != expected {
+        return Err(format!(
+            "layer `{name}` has {} tiles, the map needs {expected}",
+            data.len()
+        ));
+    }
+
+    // a tilemap draws from a single image, so every tileset gets its own
+    let mut by_tileset: HashMap<usize, Vec<RawTile>> = HashMap::new();
+    for (cell, &gid) in data.iter().enumerate() {
+        let id = gid
+            & !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+        if id == 0 {
+            continue;
+        }
+        let found = tilesets
+            .iter()
+            .rposition(|tileset| tileset.firstgid <= id)
+            .filter(|index| id - tilesets[*index].firstgid < tilesets[*index].tilecount);
+        let Some(index) = found else {
+            return Err(format!(
+                "layer `{name}` uses tile {id}, which is in no tileset"
+            ));
+        };
+        by_tileset.entry(index).or_default().push(RawTile {
+            cell: UVec2::new(cell as u32 % raw.size.x, cell as u32 / raw.size.x),
+            index: id - tilesets[index].firstgid,
+            flip: TileFlip {
+                x: gid & FLIPPED_HORIZONTALLY != 0,
+                y: gid & FLIPPED_VERTICALLY != 0,
+                d: gid & FLIPPED_DIAGONALLY != 0,
+            },
+        });
+    }
+
+    let mut indices: Vec<usize> = by_tileset.keys().copied().collect();
+    indices.sort();
+    for index in indices {
+        let tileset = &tilesets[index];
+        raw.layers.push(RawLayer {
+            image: tileset.image.clone(),
+            tile_size: tileset.tile_size,
+            spacing: tileset.spacing,
+            tiles: by_tileset.remove(&index).unwrap_or_default(),
+        });
+    }
+    Ok(())
+}
+
+// .tmx and .tsx files, attributes are prefixed with @
+mod xml {
+    use serde::{de::IgnoredAny, Deserialize};
+
+    #[derive(Deserialize)]
+    pub struct Header {
+        #[serde(rename = "@orientation")]
+        orientation: String,
+        #[serde(rename = "@infinite", default)]
+        infinite: u8,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Map {
+        #[serde(rename = "@width")]
+        width: u32,
+        #[serde(rename = "@height")]
+        height: u32,
+        #[serde(rename = "@tilewidth")]
+        tilewidth: u32,
+        #[serde(rename = "@tileheight")]
+        tileheight: u32,
+        #[serde(rename = "$value", default)]
+        children: Vec<Child>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Child {
+        Tileset(Tileset),
+        Layer(Layer),
+        ObjectGroup(ObjectGroup),
+        Group(Group),
+        #[serde(other)]
+        Other,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Tileset {
+        #[serde(rename = "@firstgid", default)]
+        firstgid: u32,
+        #[serde(rename = "@source", default)]
+        source: Option<String>,
+        #[serde(rename = "@name", default)]
+        name: String,
+        #[serde(rename = "@tilewidth", default)]
+        tilewidth: u32,
+        #[serde(rename = "@tileheight", default)]
+        tileheight: u32,
+        #[serde(rename = "@tilecount", default)]
+        tilecount: u32,
+        #[serde(rename = "@spacing", default)]
+        spacing: u32,
+        #[serde(rename = "@margin", default)]
+        margin: u32,
+        image: Option<Image>,
+    }
+
+    #[derive(Deserialize)]
+    struct Image {
+        #[serde(rename = "@source")]
+        source: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Layer {
+        #[serde(rename = "@name", default)]
+        name: String,
+        data: Data,
+    }
+
+    #[derive(Deserialize)]
+    struct Data {
+        #[serde(rename = "@encoding", default)]
+        encoding: Option<String>,
+        #[serde(rename = "$text", default)]
+        text: String,
+    }
+
+    #[derive(Deserialize)]
+    struct ObjectGroup {
+        #[serde(default)]
+        object: Vec<Object>,
+    }
+
+    #[derive(Deserialize)]
+    struct Object {
+        #[serde(rename = "@name", default)]
+        name: String,
+        #[serde(rename = "@type", default)]
+        kind: String,
+        #[serde(rename = "@class", default)]
+        class: String,
+        #[serde(rename = "@x")]
+        x: f32,
+        #[serde(rename = "@y")]
+        y: f32,
+        #[serde(rename = "@width", default)]
+        width: f32,
+        #[serde(rename = "@height", default)]
+        height: f32,
+        #[serde(rename = "@gid", default)]
+        gid: Option<u32>,
+    }
+
+    #[derive(Deserialize)]
+    struct Group {
+        #[serde(rename = "$value", default)]
+        children: Vec<Child>,
+    }
+
+    impl Header {
+        pub fn into_tiled(self) -> super::Header {
+            super::Header {
+                orientation: self.orientation,
+                infinite: self.infinite != 0,
+            }
+        }
+    }
+
+    impl Map {
+        pub fn into_tiled(self) -> Result<super::TiledMap, String> {
+            let mut tilesets = Vec::new();
+            for child in &self.children {
+                if let Child::Tileset(tileset) = child {
+                    tilesets.push(tileset.reference()?);
+                }
+            }
+            Ok(super::TiledMap {
+                width: self.width,
+                height: self.height,
+                tilewidth: self.tilewidth,
+                tileheight: self.tileheight,
+                tilesets,
+                layers: into_layers(self.children)?,
+            })
+        }
+    }
+
+    impl Tileset {
+        fn reference(&self) -> Result<super::TilesetRef, String> {
+            let firstgid = self.firstgid;
+            Ok(match &self.source {
+                Some(source) => super::TilesetRef::External {
+                    firstgid,
+                    source: source.clone(),
+                },
+                None => super::TilesetRef::Embedded {
+                    firstgid,
+                    tileset: self.to_tiled()?,
+                },
+            })
+        }
+
+        pub fn to_tiled(&self) -> Result<super::Tileset, String> {
+            let Some(image) = &self.image else {
+                return Err(format!(
+                    "tileset `{}` is a collection of images, use a single tileset image",
+                    self.name
+                ));
+            };
+            Ok(super::Tileset {
+                name: self.name.clone(),
+                image: image.source.clone(),
+                tilewidth: self.tilewidth,
+                tileheight: self.tileheight,
+                tilecount: self.tilecount,
+                spacing: self.spacing,
+                margin: self.margin,
+            })
+        }
+    }
+
+    fn into_layers(children: Vec<Child>) -> Result<Vec<super::TiledLayer>, String> {
+        let mut layers = Vec::new();
+        for child in children {
+            layers.push(match child {
+                Child::Layer(layer) => {
+                    let data = match layer.data.encoding.as_deref() {
+                        Some("csv") => {
+                            super::LayerData::Csv(parse_csv(&layer.name, &layer.data.text)?)
+                        }
+                        _ => super::LayerData::Encoded(IgnoredAny),
+                    };
+                    super::TiledLayer::TileLayer {
+                        name: layer.name,
+                        data,
+                    }
+                }
+                Child::ObjectGroup(group) => super::TiledLayer::ObjectGroup {
+                    objects: group
+                        .object
+                        .into_iter()
+                        .map(|object| super::TiledObject {
+                            name: object.name,
+                            kind: object.kind,
+                            class: object.class,
+                            x: object.x,
+                            y: object.y,
+                            width: object.width,
+                            height: object.height,
+                            gid: object.gid,
+                        })
+                        .collect(),
+                },
+                Child::Group(group) => super::TiledLayer::Group {
+                    layers: into_layers(group.children)?,
+                },
+                Child::Tileset(_) | Child::Other => continue,
+            });
+        }
+        Ok(layers)
+    }
+
+    fn parse_csv(name: &str, text: &str) -> Result<Vec<u32>, String> {
+        text.split(',')
+            .map(|gid| {
+                gid.trim()
+                    .parse()
+                    .map_err(|_| format!("layer `{name}` has an invalid tile `{}`", gid.trim()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_infinite_maps_before_their_layers() {
+        let tmj = r#"{
+            "orientation": "orthogonal", "infinite": true,
+            "width": 16, "height": 16, "tilewidth": 32, "tileheight": 32,
+            "layers": [{"type": "tilelayer", "name": "Ground",
+                "chunks": [{"x": 0, "y": 0, "width": 2, "height": 1, "data": [1, 2]}]}]
+        }"#;
+        let err = parse_tmj("arenas/test.tmj", tmj).err().unwrap();
+        assert!(err.contains("infinite maps are not supported"), "{err}");
+
+        let tmx = r#"<map orientation="orthogonal" infinite="1" width="16" height="16" tilewidth="32" tileheight="32">
+            <layer name="Ground"><data encoding="csv"><chunk x="0" y="0" width="2" height="1">1,2</chunk></data></layer>
+        </map>"#;
+        let err = parse_tmx("arenas/test.tmx", tmx).err().unwrap();
+        assert!(err.contains("infinite maps are not supported"), "{err}");
+    }
+
+    #[test]
+    fn rejects_other_orientations() {
+        let tmx = r#"<map orientation="isometric" width="2" height="1" tilewidth="32" tileheight="32">
+            <layer name="Ground"><data encoding="csv">1,2</data></layer>
+        </map>"#;
+        let err = parse_tmx("arenas/test.tmx", tmx).err().unwrap();
+        assert!(err.contains("isometric maps are not supported"), "{err}");
+    }
+
+    const TERRAIN: &str = r#"{"firstgid": 1, "name": "terrain", "image": "../textures/terrain_tiles.png",
+        "tilewidth": 32, "tileheight": 32, "tilecount": 4}"#;
+    const GRASS: &str = r#"{"firstgid": 5, "name": "grass", "image": "../textures/grass_tile.png",
+        "tilewidth": 32, "tileheight": 32, "tilecount": 1}"#;
+
+    // 2 by 2 tiles, with one tile layer
+    fn map(tilesets: &str, data: &str) -> TiledMap {
+        serde_json::from_str(&format!(
+            r#"{{"width": 2, "height": 2, "tilewidth": 32, "tileheight": 32,
+                "tilesets": [{tilesets}],
+                "layers": [{{"type": "tilelayer", "name": "Ground", "data": [{data}]}}]}}"#
+        ))
+        .unwrap()
+    }
+
+    fn tiles(layer: &RawLayer) -> Vec<(UVec2, u32, TileFlip)> {
+        layer
+            .tiles
+            .iter()
+            .map(|tile| (tile.cell, tile.index, tile.flip))
+            .collect()
+    }
+
+    #[test]
+    fn splits_layers_by_tileset_and_reads_flip_bits() {
+        let flipped_x = 1 | FLIPPED_HORIZONTALLY;
+        let flipped_yd = 3 | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY;
+        let map = map(
+            &format!("{GRASS}, {TERRAIN}"),
+            &format!("{flipped_x}, 5, 0, {flipped_yd}"),
+        );
+        let raw = convert("arenas/test.tmj", map).unwrap();
+
+        assert_eq!(raw.layers.len(), 2);
+        assert_eq!(raw.layers[0].image, "textures/terrain_tiles.png");
+        assert_eq!(
+            tiles(&raw.layers[0]),
+            vec![
+                (
+                    UVec2::new(0, 0),
+                    0,
+                    TileFlip {
+                        x: true,
+                        y: false,
+                        d: false
+                    }
+                ),
+                (
+                    UVec2::new(1, 1),
+                    2,
+                    TileFlip {
+                        x: false,
+                        y: true,
+                        d: true
+                    }
+                ),
+            ]
+        );
+        assert_eq!(raw.layers[1].image, "textures/grass_tile.png");
+        assert_eq!(
+            tiles(&raw.layers[1]),
+            vec![(UVec2::new(1, 0), 0, TileFlip::default())]
+        );
+    }
+
+    #[test]
+    fn rejects_tiles_in_no_tileset() {
+        // past the end of the only tileset
+        let err = convert("arenas/test.tmj", map(TERRAIN, "1, 2, 3, 5"))
+            .err()
+            .unwrap();
+        assert!(err.contains("uses tile 5, which is in no tileset"), "{err}");
+    }
+
+    #[test]
+    fn rejects_missing_tilesets_and_images() {
+        let external = r#"{"firstgid": 1, "source": "missing.tsj"}"#;
+        let err = convert("arenas/test.tmj", map(external, "1, 1, 1, 1"))
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("tileset `missing.tsj` does not exist"),
+            "{err}"
+        );
+
+        let no_image = TERRAIN.replace("terrain_tiles.png", "missing.png");
+        let err = convert("arenas/test.tmj", map(&no_image, "1, 1, 1, 1"))
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("image `../textures/missing.png` of tileset `terrain` does not exist"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn reads_csv_tmx_maps() {
+        let tmx = r#"<map orientation="orthogonal" width="2" height="2" tilewidth="32" tileheight="32">
+            <tileset firstgid="1" name="terrain" tilewidth="32" tileheight="32" tilecount="4">
+                <image source="../textures/terrain_tiles.png"/>
+            </tileset>
+            <layer name="Ground"><data encoding="csv">
+                1,2,
+                3,4
+            </data></layer>
+            <objectgroup><object type="PlayerSpawn" x="16" y="48"/></objectgroup>
+        </map>"#;
+        let raw = parse_tmx("arenas/test.tmx", tmx).unwrap();
+        let indices: Vec<u32> = raw.layers[0].tiles.iter().map(|tile| tile.index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3]);
+        assert_eq!(raw.objects[0].kind, "PlayerSpawn");
+        assert_eq!(raw.objects[0].position, Vec2::new(16., 48.));
+    }
+}
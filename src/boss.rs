@@ -0,0 +1,274 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::components::{BlockedByObstacles, Collider, Health, Knockback, Shootable, Velocity};
+use crate::enemy::{
+    prepare_enemy, prepare_enemy_projectile, ContactDamage, Enemy, EnemyKind, XpValue,
+};
+use crate::hazard::{Mired, MUD_SPEED_MULTIPLIER};
+use crate::loot::{spawn_drops, LootContext, LootTables};
+use crate::player::Player;
+use crate::resources::AppState::InGame;
+use crate::resources::XP;
+use crate::rift::random_point_on_unit_circle;
+use crate::seed::{DropRng, SpawnRng};
+use crate::ui::Score;
+
+const BOSS_SIZE: Vec2 = Vec2::new(150.0, 150.0);
+// how far from arena walls a boss has to appear to fit inside them
+pub const BOSS_BOUNDS_MARGIN: f32 = 90.0;
+const BOSS_COLOR: Color = Color::MAROON;
+const BOSS_HEALTH: f32 = 60.;
+const BOSS_SCORE: i32 = 50;
+const BOSS_CONTACT_DAMAGE: f32 = 2.;
+const BOSS_ATTACK_COOLDOWN: Duration = Duration::from_millis(1500);
+
+const CHARGE_SPEED: f32 = 700.0;
+const CHARGE_DURATION: Duration = Duration::from_millis(600);
+const MINION_SPAWN_RADIUS: f32 = 120.0;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (update_boss_phase, boss_attack, move_boss, boss_dies).run_if(in_state(InGame)),
+        );
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum BossAttack {
+    Charge,
+    ProjectileRing { count: u32, speed: f32 },
+    SpawnMinions { count: u32 },
+}
+
+pub struct BossPhase {
+    // the phase is active while health is at or below this fraction of max health
+    health_threshold: f32,
+    move_speed: f32,
+    attack_interval: Duration,
+    attacks: Vec<BossAttack>,
+}
+
+#[derive(Component)]
+pub struct Boss {
+    phases: Vec<BossPhase>,
+    current_phase: usize,
+    next_attack: usize,
+    attack_timer: Timer,
+}
+
+impl Boss {
+    fn new(phases: Vec<BossPhase>) -> Self {
+        let attack_timer = Timer::new(phases[0].attack_interval, TimerMode::Repeating);
+        Self {
+            phases,
+            current_phase: 0,
+            next_attack: 0,
+            attack_timer,
+        }
+    }
+
+    fn phase(&self) -> &BossPhase {
+        &self.phases[self.current_phase]
+    }
+
+    fn phase_for_health(&self, health: &Health) -> usize {
+        let fraction = health.current / health.max;
+        self.phases
+            .iter()
+            .rposition(|phase| fraction <= phase.health_threshold)
+            .unwrap_or(0)
+    }
+}
+
+fn default_boss_phases() -> Vec<BossPhase> {
+    vec![
+        BossPhase {
+            health_threshold: 1.0,
+            move_speed: 60.,
+            attack_interval: Duration::from_secs(3),
+            attacks: vec![BossAttack::Charge],
+        },
+        BossPhase {
+            health_threshold: 0.66,
+            move_speed: 40.,
+            attack_interval: Duration::from_millis(2500),
+            attacks: vec![
+                BossAttack::ProjectileRing {
+                    count: 12,
+                    speed: 250.,
+                },
+                BossAttack::Charge,
+            ],
+        },
+        BossPhase {
+            health_threshold: 0.33,
+            move_speed: 80.,
+            attack_interval: Duration::from_secs(2),
+            attacks: vec![
+                BossAttack::SpawnMinions { count: 4 },
+                BossAttack::ProjectileRing {
+                    count: 16,
+                    speed: 300.,
+                },
+                BossAttack::Charge,
+            ],
+        },
+    ]
+}
+
+#[derive(Component)]
+struct Charging {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+pub fn spawn_boss(commands: &mut Commands, location: Vec2) {
+    commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_xyz(location.x, location.y, 0.),
+            sprite: Sprite {
+                color: BOSS_COLOR,
+                custom_size: Some(BOSS_SIZE),
+                ..default()
+            },
+            ..default()
+        },
+        Collider(BOSS_SIZE),
+        Enemy,
+        Health {
+            current: BOSS_HEALTH,
+            max: BOSS_HEALTH,
+        },
+        Velocity(Vec2::ZERO),
+        XpValue(10.),
+        ContactDamage::new(BOSS_CONTACT_DAMAGE, BOSS_ATTACK_COOLDOWN),
+        Boss::new(default_boss_phases()),
+        Shootable,
+        BlockedByObstacles,
+    ));
+}
+
+fn update_boss_phase(mut q_boss: Query<(&mut Boss, &Health)>) {
+    for (mut boss, health) in q_boss.iter_mut() {
+        let phase = boss.phase_for_health(health);
+        if phase != boss.current_phase {
+            boss.current_phase = phase;
+            boss.next_attack = 0;
+            let interval = boss.phase().attack_interval;
+            boss.attack_timer = Timer::new(interval, TimerMode::Repeating);
+        }
+    }
+}
+
+fn boss_attack(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_boss: Query<(Entity, &Transform, &mut Boss), Without<Charging>>,
+    q_player: Query<&Transform, With<Player>>,
+    mut rng: ResMut<SpawnRng>,
+) {
+    let player_position = q_player.single().translation.truncate();
+    for (entity, transform, mut boss) in q_boss.iter_mut() {
+        boss.attack_timer.tick(time.delta());
+        if !boss.attack_timer.just_finished() {
+            continue;
+        }
+
+        let boss_position = transform.translation.truncate();
+        let attacks = &boss.phase().attacks;
+        let attack = attacks[boss.next_attack % attacks.len()];
+        boss.next_attack += 1;
+
+        match attack {
+            BossAttack::Charge => {
+                let direction = (player_position - boss_position).normalize_or_zero();
+                commands.entity(entity).insert(Charging {
+                    velocity: direction * CHARGE_SPEED,
+                    timer: Timer::new(CHARGE_DURATION, TimerMode::Once),
+                });
+            }
+            BossAttack::ProjectileRing { count, speed } => {
+                for i in 0..count {
+                    let direction = Vec2::from_angle(TAU * i as f32 / count as f32);
+                    commands.spawn(prepare_enemy_projectile(boss_position, direction * speed));
+                }
+            }
+            BossAttack::SpawnMinions { count } => {
+                for _ in 0..count {
+                    let location = boss_position
+                        + random_point_on_unit_circle(MINION_SPAWN_RADIUS, &mut rng.0);
+                    commands.spawn(prepare_enemy(EnemyKind::Grunt, &location));
+                }
+            }
+        }
+    }
+}
+
+fn move_boss(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_boss: Query<
+        (
+            Entity,
+            &Transform,
+            &Boss,
+            &mut Velocity,
+            Option<&mut Charging>,
+            Has<Mired>,
+        ),
+        Without<Knockback>,
+    >,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    let player_position = q_player.single().translation.truncate();
+    for (entity, transform, boss, mut velocity, charging, mired) in q_boss.iter_mut() {
+        if let Some(mut charging) = charging {
+            velocity.0 = charging.velocity;
+            charging.timer.tick(time.delta());
+            if charging.timer.finished() {
+                commands.entity(entity).remove::<Charging>();
+            }
+            continue;
+        }
+
+        let direction = (player_position - transform.translation.truncate()).normalize_or_zero();
+        let speed = if mired {
+            boss.phase().move_speed * MUD_SPEED_MULTIPLIER
+        } else {
+            boss.phase().move_speed
+        };
+        velocity.0 = direction * speed;
+    }
+}
+
+fn boss_dies(
+    mut commands: Commands,
+    q_boss: Query<(Entity, &Health, &Transform, &XpValue), With<Boss>>,
+    mut score: ResMut<Score>,
+    loot_tables: Res<LootTables>,
+    xp: Res<XP>,
+    mut rng: ResMut<DropRng>,
+) {
+    for (entity, health, transform, xp_value) in q_boss.iter() {
+        if health.current <= 0. {
+            if let Some(table) = &loot_tables.boss_table {
+                let context = LootContext {
+                    player_level: xp.level(),
+                    elite: true,
+                };
+                let drops = loot_tables.roll(table, &context, &mut rng.0);
+                let position = transform.translation.truncate();
+                spawn_drops(&mut commands, position, xp_value.0, &drops, &mut rng.0);
+            }
+            commands.entity(entity).despawn();
+            score.0 += BOSS_SCORE;
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::resources::AppState::InGame;
+
+const WALL_THICKNESS: f32 = 24.0;
+const WALL_COLOR: Color = Color::rgb(0.25, 0.22, 0.2);
+const WALL_Z: f32 = 1.0;
+const CIRCLE_WALL_SEGMENTS: usize = 96;
+
+pub struct BoundsPlugin;
+
+impl Plugin for BoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(InGame),
+            spawn_walls.run_if(resource_exists::<ArenaBounds>().and_then(run_once())),
+        );
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum BoundsShape {
+    Rectangle { width: f32, height: f32 },
+    Circle { radius: f32 },
+}
+
+impl BoundsShape {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            BoundsShape::Rectangle { width, height } if width <= 0. || height <= 0. => Err(
+                format!("bounds must have a positive size, got {width}x{height}"),
+            ),
+            BoundsShape::Circle { radius } if radius <= 0. => {
+                Err(format!("bounds must have a positive radius, got {radius}"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// a finite play area, nothing that walls stop can leave it
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ArenaBounds {
+    pub shape: BoundsShape,
+    pub center: Vec2,
+}
+
+impl ArenaBounds {
+    fn half_extents(&self) -> Vec2 {
+        match self.shape {
+            BoundsShape::Rectangle { width, height } => Vec2::new(width, height) / 2.,
+            BoundsShape::Circle { radius } => Vec2::splat(radius),
+        }
+    }
+
+    pub fn contains(&self, point: Vec2, margin: f32) -> bool {
+        let offset = point - self.center;
+        match self.shape {
+            BoundsShape::Rectangle { .. } => {
+                (offset.abs() + margin).cmple(self.half_extents()).all()
+            }
+            BoundsShape::Circle { radius } => offset.length() + margin <= radius,
+        }
+    }
+
+    // the closest position where a box of `size` is fully inside
+    pub fn clamp(&self, point: Vec2, size: Vec2) -> Vec2 {
+        let half_size = size / 2.;
+        let offset = point - self.center;
+        let clamped = match self.shape {
+            BoundsShape::Rectangle { .. } => {
+                let room = (self.half_extents() - half_size).max(Vec2::ZERO);
+                offset.clamp(-room, room)
+            }
+            BoundsShape::Circle { radius } => {
+                offset.clamp_length_max((radius - half_size.max_element()).max(0.))
+            }
+        };
+        self.center + clamped
+    }
+
+    // where a camera showing `view_size` can be without looking past the walls,
+    // a view larger than the arena just stays centered on it
+    pub fn clamp_view(&self, point: Vec2, view_size: Vec2) -> Vec2 {
+        let room = (self.half_extents() + WALL_THICKNESS - view_size / 2.).max(Vec2::ZERO);
+        self.center + (point - self.center).clamp(-room, room)
+    }
+
+    pub fn random_point(&self, margin: f32, rng: &mut impl Rng) -> Vec2 {
+        let offset = match self.shape {
+            BoundsShape::Rectangle { .. } => {
+                let room = (self.half_extents() - margin).max(Vec2::ZERO);
+                Vec2::new(
+                    rng.gen_range(-room.x..=room.x),
+                    rng.gen_range(-room.y..=room.y),
+                )
+            }
+            BoundsShape::Circle { radius } => {
+                // the square root spreads points evenly over the area
+                let distance = (radius - margin).max(0.) * rng.gen::<f32>().sqrt();
+                Vec2::from_angle(rng.gen_range(0.0..TAU)) * distance
+            }
+        };
+        self.center + offset
+    }
+
+    // spawn points too close to the walls or past them are rolled again somewhere inside
+    pub fn keep_inside(&self, point: Vec2, margin: f32, rng: &mut impl Rng) -> Vec2 {
+        if self.contains(point, margin) {
+            point
+        } else {
+            self.random_point(margin, rng)
+        }
+    }
+}
+
+fn spawn_walls(mut commands: Commands, bounds: Res<ArenaBounds>) {
+    let mut walls = Vec::new();
+    match bounds.shape {
+        BoundsShape::Rectangle { width, height } => {
+            let half = Vec2::new(width, height) / 2. + WALL_THICKNESS / 2.;
+            let horizontal = Vec2::new(width + WALL_THICKNESS * 2., WALL_THICKNESS);
+            let vertical = Vec2::new(WALL_THICKNESS, height);
+            walls.push((Vec2::new(0., half.y), horizontal, 0.));
+            walls.push((Vec2::new(0., -half.y), horizontal, 0.));
+            walls.push((Vec2::new(half.x, 0.), vertical, 0.));
+            walls.push((Vec2::new(-half.x, 0.), vertical, 0.));
+        }
+        BoundsShape::Circle { radius } => {
+            let ring = radius + WALL_THICKNESS / 2.;
+            // segments overlap a little so the ring has no gaps
+            let length = TAU * ring / CIRCLE_WALL_SEGMENTS as f32 + 2.;
+            for i in 0..CIRCLE_WALL_SEGMENTS {
+                let angle = TAU * i as f32 / CIRCLE_WALL_SEGMENTS as f32;
+                walls.push((
+                    Vec2::from_angle(angle) * ring,
+                    Vec2::new(WALL_THICKNESS, length),
+                    angle,
+                ));
+            }
+        }
+    }
+
+    for (offset, size, angle) in walls {
+        let position = (bounds.center + offset).extend(WALL_Z);
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: WALL_COLOR,
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(position)
+                .with_rotation(Quat::from_rotation_z(angle)),
+            ..default()
+        });
+    }
+}
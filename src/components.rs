@@ -32,3 +32,16 @@ pub struct Knockback {
 
 #[derive(Component)]
 pub struct XpGem(pub f32);
+
+#[derive(Component)]
+pub struct Chest {
+    pub xp: f32,
+}
+
+// anything player bullets can hit
+#[derive(Component)]
+pub struct Shootable;
+
+// stopped by solid tiles instead of moving through them
+#[derive(Component)]
+pub struct BlockedByObstacles;
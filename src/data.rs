@@ -0,0 +1,39 @@
+use std::path::{Component, Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use serde::de::DeserializeOwned;
+
+// data files live next to the textures, in the assets folder bevy loads from
+pub fn asset_path(relative: &str) -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(relative)
+}
+
+// a path written inside the asset `from`, as a path from the assets folder
+pub fn relative_asset_path(from: &str, path: &str) -> String {
+    let joined = Path::new(from).parent().unwrap_or(Path::new("")).join(path);
+    let mut parts: Vec<String> = Vec::new();
+    for component in joined.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().into_owned()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+pub fn read_asset(relative: &str) -> Result<String, String> {
+    let path = asset_path(relative);
+    std::fs::read_to_string(&path)
+        .map_err(|err| format!("could not read {}: {err}", path.display()))
+}
+
+pub fn load_ron<T: DeserializeOwned>(relative: &str) -> Result<T, String> {
+    let source = read_asset(relative)?;
+    ron::from_str(&source)
+        .map_err(|err| format!("could not parse {}: {err}", asset_path(relative).display()))
+}
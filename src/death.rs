@@ -0,0 +1,312 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::components::Health;
+use crate::enemy::{die, prepare_enemy, Disposable, Enemy, EnemyKind};
+use crate::events::DamagePlayer;
+use crate::loot::{spawn_drops, LootContext, LootTables};
+use crate::player::{Player, PlayerDamageSet};
+use crate::resources::AppState::InGame;
+use crate::resources::XP;
+use crate::rift::random_point_on_unit_circle;
+use crate::seed::{DropRng, SpawnRng};
+
+const SPLIT_SPAWN_RADIUS: f32 = 30.0;
+const EXPLOSION_DURATION: Duration = Duration::from_millis(250);
+const POISON_TICK: Duration = Duration::from_millis(500);
+
+pub struct DeathPlugin;
+
+impl Plugin for DeathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnemyDied>()
+            .add_systems(
+                Update,
+                (
+                    // every death handler reacts to the same EnemyDied events
+                    (drop_loot, split, leave_poison_puddle, arm_fuse, heal_allies).after(die),
+                    fade_explosions,
+                )
+                    .run_if(in_state(InGame)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (tick_fuses, tick_poison_puddles)
+                    .before(PlayerDamageSet)
+                    .run_if(in_state(InGame)),
+            );
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum DeathBehavior {
+    DropLoot,
+    Split {
+        kind: EnemyKind,
+        count: u32,
+        scale: f32,
+    },
+    PoisonPuddle {
+        radius: f32,
+        damage: f32,
+        duration: Duration,
+    },
+    Explode {
+        fuse: Duration,
+        radius: f32,
+        damage: f32,
+    },
+    HealAllies {
+        radius: f32,
+        amount: f32,
+    },
+}
+
+#[derive(Component, Clone)]
+pub struct OnDeath(pub Vec<DeathBehavior>);
+
+#[derive(Event)]
+pub struct EnemyDied {
+    pub position: Vec2,
+    pub kind: EnemyKind,
+    pub xp: f32,
+    pub elite: bool,
+    pub behaviors: Vec<DeathBehavior>,
+}
+
+// spawned by a dying enemy, never promoted to an elite
+#[derive(Component)]
+pub struct SplitOffspring;
+
+#[derive(Component)]
+struct Fuse {
+    timer: Timer,
+    radius: f32,
+    damage: f32,
+}
+
+#[derive(Component)]
+struct Explosion {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct PoisonPuddle {
+    radius: f32,
+    damage: f32,
+    lifetime: Timer,
+    tick: Timer,
+}
+
+fn drop_loot(
+    mut commands: Commands,
+    mut ev_enemy_died: EventReader<EnemyDied>,
+    loot_tables: Res<LootTables>,
+    xp: Res<XP>,
+    mut rng: ResMut<DropRng>,
+) {
+    for ev in ev_enemy_died.read() {
+        if !ev
+            .behaviors
+            .iter()
+            .any(|behavior| matches!(behavior, DeathBehavior::DropLoot))
+        {
+            continue;
+        }
+        let Some(table) = loot_tables.enemy_table(ev.kind) else {
+            continue;
+        };
+        let context = LootContext {
+            player_level: xp.level(),
+            elite: ev.elite,
+        };
+        let drops = loot_tables.roll(table, &context, &mut rng.0);
+        spawn_drops(&mut commands, ev.position, ev.xp, &drops, &mut rng.0);
+    }
+}
+
+fn split(
+    mut commands: Commands,
+    mut ev_enemy_died: EventReader<EnemyDied>,
+    mut rng: ResMut<SpawnRng>,
+) {
+    for ev in ev_enemy_died.read() {
+        for behavior in ev.behaviors.iter() {
+            let DeathBehavior::Split { kind, count, scale } = *behavior else {
+                continue;
+            };
+            for _ in 0..count {
+                let location =
+                    ev.position + random_point_on_unit_circle(SPLIT_SPAWN_RADIUS, &mut rng.0);
+                let enemy = prepare_enemy(kind, &location).scaled(scale);
+                commands.spawn((enemy, SplitOffspring, Disposable));
+            }
+        }
+    }
+}
+
+fn leave_poison_puddle(mut commands: Commands, mut ev_enemy_died: EventReader<EnemyDied>) {
+    for ev in ev_enemy_died.read() {
+        for behavior in ev.behaviors.iter() {
+            let DeathBehavior::PoisonPuddle {
+                radius,
+                damage,
+                duration,
+            } = *behavior
+            else {
+                continue;
+            };
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::OLIVE.with_a(0.5),
+                        custom_size: Some(Vec2::splat(radius * 2.)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(ev.position.extend(-1.)),
+                    ..default()
+                },
+                PoisonPuddle {
+                    radius,
+                    damage,
+                    lifetime: Timer::new(duration, TimerMode::Once),
+                    tick: Timer::new(POISON_TICK, TimerMode::Repeating),
+                },
+            ));
+        }
+    }
+}
+
+fn arm_fuse(mut commands: Commands, mut ev_enemy_died: EventReader<EnemyDied>) {
+    for ev in ev_enemy_died.read() {
+        for behavior in ev.behaviors.iter() {
+            let DeathBehavior::Explode {
+                fuse,
+                radius,
+                damage,
+            } = *behavior
+            else {
+                continue;
+            };
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::ORANGE,
+                        custom_size: Some(Vec2::splat(20.)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(ev.position.extend(1.)),
+                    ..default()
+                },
+                Fuse {
+                    timer: Timer::new(fuse, TimerMode::Once),
+                    radius,
+                    damage,
+                },
+            ));
+        }
+    }
+}
+
+fn heal_allies(
+    mut ev_enemy_died: EventReader<EnemyDied>,
+    mut q_enemy: Query<(&Transform, &mut Health), With<Enemy>>,
+) {
+    for ev in ev_enemy_died.read() {
+        for behavior in ev.behaviors.iter() {
+            let DeathBehavior::HealAllies { radius, amount } = *behavior else {
+                continue;
+            };
+            for (transform, mut health) in q_enemy.iter_mut() {
+                if health.current > 0.
+                    && transform.translation.truncate().distance(ev.position) <= radius
+                {
+                    health.current = (health.current + amount).min(health.max);
+                }
+            }
+        }
+    }
+}
+
+fn tick_fuses(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_fuse: Query<(Entity, &Transform, &mut Fuse, &mut Visibility)>,
+    q_player: Query<&Transform, With<Player>>,
+    mut ev_damage_player: EventWriter<DamagePlayer>,
+) {
+    let player_position = q_player.single().translation.truncate();
+    for (entity, transform, mut fuse, mut visibility) in q_fuse.iter_mut() {
+        fuse.timer.tick(time.delta());
+        *visibility = if (fuse.timer.elapsed_secs() * 10.) as u32 % 2 == 0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if !fuse.timer.finished() {
+            continue;
+        }
+
+        let position = transform.translation.truncate();
+        commands.entity(entity).despawn();
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::ORANGE.with_a(0.6),
+                    custom_size: Some(Vec2::splat(fuse.radius * 2.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(1.)),
+                ..default()
+            },
+            Explosion {
+                timer: Timer::new(EXPLOSION_DURATION, TimerMode::Once),
+            },
+        ));
+        if position.distance(player_position) <= fuse.radius {
+            ev_damage_player.send(DamagePlayer {
+                amount: fuse.damage,
+            });
+        }
+    }
+}
+
+fn tick_poison_puddles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_puddle: Query<(Entity, &Transform, &mut PoisonPuddle)>,
+    q_player: Query<&Transform, With<Player>>,
+    mut ev_damage_player: EventWriter<DamagePlayer>,
+) {
+    let player_position = q_player.single().translation.truncate();
+    for (entity, transform, mut puddle) in q_puddle.iter_mut() {
+        puddle.lifetime.tick(time.delta());
+        puddle.tick.tick(time.delta());
+        if puddle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if puddle.tick.just_finished()
+            && transform.translation.truncate().distance(player_position) <= puddle.radius
+        {
+            ev_damage_player.send(DamagePlayer {
+                amount: puddle.damage,
+            });
+        }
+    }
+}
+
+fn fade_explosions(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_explosion: Query<(Entity, &mut Explosion, &mut Sprite)>,
+) {
+    for (entity, mut explosion, mut sprite) in q_explosion.iter_mut() {
+        explosion.timer.tick(time.delta());
+        sprite.color.set_a(0.6 * explosion.timer.percent_left());
+        if explosion.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::boss::Boss;
+use crate::components::{Health, Movable};
+use crate::enemy::{prepare_enemy, Enemy, EnemyKind};
+use crate::player::Player;
+use crate::resources::AppState::InGame;
+use crate::rift::RiftKind;
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DirectorConfig>()
+            .add_systems(OnEnter(InGame), setup_director)
+            .add_systems(
+                Update,
+                (
+                    tick_director,
+                    (scale_new_enemies, run_surges).after(tick_director),
+                )
+                    .run_if(in_state(InGame)),
+            );
+    }
+}
+
+// one point on the difficulty curve, values are interpolated between points
+#[derive(Clone)]
+pub struct DifficultyKey {
+    pub at: Duration,
+    pub rift_interval: f32,
+    // threat points the director banks per second for rifts to spend
+    pub threat_per_second: f32,
+    pub health_multiplier: f32,
+    pub speed_multiplier: f32,
+    pub kind_weights: Vec<(EnemyKind, u32)>,
+    pub rift_weights: Vec<(RiftKind, u32)>,
+}
+
+#[derive(Clone)]
+pub struct Surge {
+    pub at: Duration,
+    pub kind: EnemyKind,
+    pub count: u32,
+    pub radius: f32,
+}
+
+#[derive(Resource, Clone)]
+pub struct DirectorConfig {
+    pub curve: Vec<DifficultyKey>,
+    pub surges: Vec<Surge>,
+    // spawning holds back while this many enemies are alive
+    pub max_enemies: usize,
+    // unspent threat stops accumulating past this, so a long hold doesn't end in a flood
+    pub max_banked_threat: f32,
+}
+
+impl Default for DirectorConfig {
+    fn default() -> Self {
+        Self {
+            curve: vec![
+                DifficultyKey {
+                    at: Duration::ZERO,
+                    rift_interval: 4.,
+                    threat_per_second: 1.25,
+                    health_multiplier: 1.,
+                    speed_multiplier: 1.,
+                    kind_weights: vec![(EnemyKind::Grunt, 1)],
+                    rift_weights: vec![(RiftKind::Standard, 1)],
+                },
+                DifficultyKey {
+                    at: Duration::from_secs(60),
+                    rift_interval: 3.5,
+                    threat_per_second: 1.7,
+                    health_multiplier: 1.,
+                    speed_multiplier: 1.,
+                    kind_weights: vec![
+                        (EnemyKind::Grunt, 70),
+                        (EnemyKind::Splitter, 15),
+                        (EnemyKind::Charger, 15),
+                    ],
+                    rift_weights: vec![(RiftKind::Standard, 8), (RiftKind::Swarm, 2)],
+                },
+                DifficultyKey {
+                    at: Duration::from_secs(180),
+                    rift_interval: 3.,
+                    threat_per_second: 2.7,
+                    health_multiplier: 1.5,
+                    speed_multiplier: 1.1,
+                    kind_weights: vec![
+                        (EnemyKind::Grunt, 60),
+                        (EnemyKind::Splitter, 8),
+                        (EnemyKind::Bloater, 6),
+                        (EnemyKind::Plaguebearer, 5),
+                        (EnemyKind::Mender, 5),
+                        (EnemyKind::Charger, 8),
+                        (EnemyKind::Kiter, 8),
+                    ],
+                    rift_weights: vec![
+                        (RiftKind::Standard, 6),
+                        (RiftKind::Swarm, 2),
+                        (RiftKind::Elite, 1),
+                        (RiftKind::Ranged, 1),
+                    ],
+                },
+                DifficultyKey {
+                    at: Duration::from_secs(600),
+                    rift_interval: 2.,
+                    threat_per_second: 6.,
+                    health_multiplier: 3.,
+                    speed_multiplier: 1.25,
+                    kind_weights: vec![
+                        (EnemyKind::Grunt, 40),
+                        (EnemyKind::Splitter, 10),
+                        (EnemyKind::Bloater, 10),
+                        (EnemyKind::Plaguebearer, 10),
+                        (EnemyKind::Mender, 10),
+                        (EnemyKind::Charger, 10),
+                        (EnemyKind::Kiter, 10),
+                    ],
+                    rift_weights: vec![
+                        (RiftKind::Standard, 5),
+                        (RiftKind::Swarm, 2),
+                        (RiftKind::Elite, 2),
+                        (RiftKind::Ranged, 2),
+                        (RiftKind::Boss, 1),
+                    ],
+                },
+            ],
+            surges: vec![
+                Surge {
+                    at: Duration::from_secs(150),
+                    kind: EnemyKind::Grunt,
+                    count: 24,
+                    radius: 600.,
+                },
+                Surge {
+                    at: Duration::from_secs(300),
+                    kind: EnemyKind::Grunt,
+                    count: 48,
+                    radius: 650.,
+                },
+                Surge {
+                    at: Duration::from_secs(480),
+                    kind: EnemyKind::Charger,
+                    count: 24,
+                    radius: 700.,
+                },
+            ],
+            max_enemies: 300,
+            max_banked_threat: 30.,
+        }
+    }
+}
+
+impl DirectorConfig {
+    // interpolating needs at least one key and keys in time order
+    pub fn validate(&self) -> Result<(), String> {
+        if self.curve.is_empty() {
+            return Err("the difficulty curve has no keys".to_string());
+        }
+        for pair in self.curve.windows(2) {
+            if pair[1].at <= pair[0].at {
+                return Err(format!(
+                    "difficulty key at {:?} does not come after the one at {:?}",
+                    pair[1].at, pair[0].at
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    pub rift_interval: Duration,
+    pub threat_per_second: f32,
+    pub health_multiplier: f32,
+    pub speed_multiplier: f32,
+}
+
+#[derive(Resource)]
+pub struct WaveDirector {
+    pub elapsed: Duration,
+    config: DirectorConfig,
+    next_surge: usize,
+    threat_budget: f32,
+}
+
+impl WaveDirector {
+    pub fn new(config: DirectorConfig) -> Self {
+        Self {
+            elapsed: Duration::ZERO,
+            config,
+            next_surge: 0,
+            threat_budget: 0.,
+        }
+    }
+
+    fn keys_around(&self) -> (&DifficultyKey, &DifficultyKey, f32) {
+        let curve = &self.config.curve;
+        let next = curve
+            .iter()
+            .position(|key| key.at > self.elapsed)
+            .unwrap_or(curve.len());
+        if next == 0 {
+            return (&curve[0], &curve[0], 0.);
+        }
+        if next == curve.len() {
+            let last = &curve[curve.len() - 1];
+            return (last, last, 0.);
+        }
+        let (from, to) = (&curve[next - 1], &curve[next]);
+        let t = (self.elapsed - from.at).as_secs_f32() / (to.at - from.at).as_secs_f32();
+        (from, to, t)
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        let (from, to, t) = self.keys_around();
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Difficulty {
+            rift_interval: Duration::from_secs_f32(lerp(from.rift_interval, to.rift_interval)),
+            threat_per_second: lerp(from.threat_per_second, to.threat_per_second),
+            health_multiplier: lerp(from.health_multiplier, to.health_multiplier),
+            speed_multiplier: lerp(from.speed_multiplier, to.speed_multiplier),
+        }
+    }
+
+    pub fn pick_enemy_kind(&self, rng: &mut impl Rng) -> EnemyKind {
+        let (from, _, _) = self.keys_around();
+        from.kind_weights
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map_or(EnemyKind::Grunt, |(kind, _)| *kind)
+    }
+
+    pub fn pick_rift_kind(&self, rng: &mut impl Rng) -> RiftKind {
+        let (from, _, _) = self.keys_around();
+        from.rift_weights
+            .choose_weighted(rng, |(_, weight)| *weight)
+            .map_or(RiftKind::Standard, |(kind, _)| *kind)
+    }
+
+    pub fn at_enemy_cap(&self, live_enemies: usize) -> bool {
+        live_enemies >= self.config.max_enemies
+    }
+
+    pub fn try_spend(&mut self, cost: f32) -> bool {
+        if self.threat_budget < cost {
+            return false;
+        }
+        self.threat_budget -= cost;
+        true
+    }
+
+    fn accumulate_threat(&mut self, delta: Duration) {
+        let income = self.difficulty().threat_per_second * delta.as_secs_f32();
+        self.threat_budget = (self.threat_budget + income).min(self.config.max_banked_threat);
+    }
+
+    fn due_surges(&mut self) -> Vec<Surge> {
+        let mut due = Vec::new();
+        while let Some(surge) = self.config.surges.get(self.next_surge) {
+            if surge.at > self.elapsed {
+                break;
+            }
+            due.push(surge.clone());
+            self.next_surge += 1;
+        }
+        due
+    }
+}
+
+fn setup_director(mut commands: Commands, config: Res<DirectorConfig>) {
+    if let Err(err) = config.validate() {
+        panic!("invalid director config: {err}");
+    }
+    commands.insert_resource(WaveDirector::new(config.clone()));
+}
+
+fn tick_director(time: Res<Time>, mut director: ResMut<WaveDirector>) {
+    director.elapsed += time.delta();
+    director.accumulate_threat(time.delta());
+}
+
+// bosses keep their own numbers, their phases are tuned against them
+fn scale_new_enemies(
+    director: Res<WaveDirector>,
+    mut q_enemy: Query<(&mut Health, Option<&mut Movable>), (Added<Enemy>, Without<Boss>)>,
+) {
+    let difficulty = director.difficulty();
+    for (mut health, movable) in q_enemy.iter_mut() {
+        health.max *= difficulty.health_multiplier;
+        health.current = health.max;
+        if let Some(mut movable) = movable {
+            movable.move_speed *= difficulty.speed_multiplier;
+        }
+    }
+}
+
+fn run_surges(
+    mut commands: Commands,
+    mut director: ResMut<WaveDirector>,
+    q_player: Query<&Transform, With<Player>>,
+    q_enemy: Query<(), With<Enemy>>,
+) {
+    let player_position = q_player.single().translation.truncate();
+    let mut live_enemies = q_enemy.iter().count();
+    for surge in director.due_surges() {
+        // a closing ring around the player, thinned out near the enemy cap
+        for i in 0..surge.count {
+            if director.at_enemy_cap(live_enemies) {
+                break;
+            }
+            live_enemies += 1;
+            let angle = TAU * i as f32 / surge.count as f32;
+            let location = player_position + Vec2::from_angle(angle) * surge.radius;
+            commands.spawn(prepare_enemy(surge.kind, &location));
+        }
+    }
+}
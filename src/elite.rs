@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::boss::Boss;
+use crate::components::{Collider, Health, Movable};
+use crate::death::{DeathBehavior, SplitOffspring};
+use crate::enemy::{Enemy, EnemyKind, XpValue};
+use crate::resources::AppState::InGame;
+use crate::seed::EliteRng;
+
+const ELITE_CHANCE: f64 = 0.08;
+const ELITE_SCALE: f32 = 1.4;
+const ELITE_XP_MULTIPLIER: f32 = 3.;
+const MAX_AFFIXES: usize = 2;
+
+const FAST_SPEED_MULTIPLIER: f32 = 1.6;
+const ARMORED_DAMAGE_REDUCTION: f32 = 0.5;
+const REGENERATION_PER_SECOND: f32 = 0.5;
+const EXPLOSION_RADIUS: f32 = 120.0;
+const EXPLOSION_DAMAGE: f32 = 2.;
+const SPLIT_COUNT: u32 = 3;
+const SPLIT_SCALE: f32 = 0.6;
+
+pub struct ElitePlugin;
+
+impl Plugin for ElitePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (promote_to_elite, regenerate).run_if(in_state(InGame)),
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct Elite;
+
+// promoted regardless of the elite chance, e.g. when spawned by an elite rift
+#[derive(Component)]
+pub struct ForceElite;
+
+#[derive(Component)]
+pub struct Fast;
+
+#[derive(Component)]
+pub struct Armored {
+    pub damage_reduction: f32,
+}
+
+#[derive(Component)]
+pub struct Regenerating {
+    pub per_second: f32,
+}
+
+#[derive(Component)]
+pub struct Explosive {
+    pub radius: f32,
+    pub damage: f32,
+}
+
+impl Explosive {
+    pub fn death_behavior(&self) -> DeathBehavior {
+        DeathBehavior::Explode {
+            fuse: Duration::ZERO,
+            radius: self.radius,
+            damage: self.damage,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Splitting {
+    pub count: u32,
+}
+
+impl Splitting {
+    pub fn death_behavior(&self) -> DeathBehavior {
+        DeathBehavior::Split {
+            kind: EnemyKind::Grunt,
+            count: self.count,
+            scale: SPLIT_SCALE,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Affix {
+    Fast,
+    Armored,
+    Regenerating,
+    Explosive,
+    Splitting,
+}
+
+const AFFIXES: [Affix; 5] = [
+    Affix::Fast,
+    Affix::Armored,
+    Affix::Regenerating,
+    Affix::Explosive,
+    Affix::Splitting,
+];
+
+impl Affix {
+    fn tint(self) -> Color {
+        match self {
+            Affix::Fast => Color::CYAN,
+            Affix::Armored => Color::SILVER,
+            Affix::Regenerating => Color::LIME_GREEN,
+            Affix::Explosive => Color::ORANGE,
+            Affix::Splitting => Color::VIOLET,
+        }
+    }
+
+    fn insert(self, entity: &mut bevy::ecs::system::EntityCommands) {
+        match self {
+            Affix::Fast => entity.insert(Fast),
+            Affix::Armored => entity.insert(Armored {
+                damage_reduction: ARMORED_DAMAGE_REDUCTION,
+            }),
+            Affix::Regenerating => entity.insert(Regenerating {
+                per_second: REGENERATION_PER_SECOND,
+            }),
+            Affix::Explosive => entity.insert(Explosive {
+                radius: EXPLOSION_RADIUS,
+                damage: EXPLOSION_DAMAGE,
+            }),
+            Affix::Splitting => entity.insert(Splitting { count: SPLIT_COUNT }),
+        };
+    }
+}
+
+fn promote_to_elite(
+    mut commands: Commands,
+    mut q_enemy: Query<
+        (
+            Entity,
+            &mut Sprite,
+            &mut Collider,
+            &mut Movable,
+            &mut XpValue,
+            Has<ForceElite>,
+        ),
+        (Added<Enemy>, Without<Boss>, Without<SplitOffspring>),
+    >,
+    mut rng: ResMut<EliteRng>,
+) {
+    let rng = &mut rng.0;
+    for (entity, mut sprite, mut collider, mut movable, mut xp, forced) in q_enemy.iter_mut() {
+        if !forced && !rng.gen_bool(ELITE_CHANCE) {
+            continue;
+        }
+
+        let affix_count = rng.gen_range(1..=MAX_AFFIXES);
+        let affixes: Vec<Affix> = AFFIXES.choose_multiple(rng, affix_count).copied().collect();
+
+        let mut entity_commands = commands.entity(entity);
+        entity_commands.insert(Elite);
+        let mut tint = Vec4::ZERO;
+        for affix in affixes.iter() {
+            affix.insert(&mut entity_commands);
+            tint += Vec4::from(affix.tint());
+            if let Affix::Fast = affix {
+                movable.move_speed *= FAST_SPEED_MULTIPLIER;
+            }
+        }
+
+        sprite.color = Color::from(tint / affixes.len() as f32);
+        sprite.custom_size = sprite.custom_size.map(|size| size * ELITE_SCALE);
+        collider.0 *= ELITE_SCALE;
+        xp.0 *= ELITE_XP_MULTIPLIER;
+    }
+}
+
+fn regenerate(time: Res<Time>, mut q_enemy: Query<(&mut Health, &Regenerating)>) {
+    for (mut health, regenerating) in q_enemy.iter_mut() {
+        if health.current > 0. {
+            health.current =
+                (health.current + regenerating.per_second * time.delta_seconds()).min(health.max);
+        }
+    }
+}
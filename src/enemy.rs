@@ -1,19 +1,39 @@
-use bevy::math::vec2;
-use bevy::{prelude::*, sprite::collide_aabb::collide};
-use rand::{thread_rng, Rng};
+use std::f32::consts::{FRAC_PI_3, TAU};
+use std::time::Duration;
 
-use crate::components::XpGem;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::ai::{AiBehavior, AiBrain};
+use crate::boss::Boss;
+use crate::bounds::ArenaBounds;
+use crate::death::{DeathBehavior, EnemyDied, OnDeath};
+use crate::elite::{Elite, Explosive, Splitting};
+use crate::flow_field::FlowField;
 use crate::resources::AppState::InGame;
+use crate::seed::SpawnRng;
 use crate::{
-    components::{Collider, Health, Knockback, Movable, Velocity},
-    player::{Bullet, Player},
+    components::{BlockedByObstacles, Collider, Health, Knockback, Movable, Shootable, Velocity},
+    player::Player,
     ui::Score,
 };
 
 const ENEMY_SIZE: Vec2 = Vec2::new(50.0, 50.0);
 const ENEMY_HEALTH: f32 = 2.;
+const ENEMY_CONTACT_DAMAGE: f32 = 1.;
+const ENEMY_ATTACK_COOLDOWN: Duration = Duration::from_secs(1);
+const ENEMY_SPEED: f32 = 100.;
 
 const MAX_ENEMY_DISTANCE: f32 = 2000.0;
+const RELOCATION_DISTANCE: f32 = 900.0;
+const RELOCATION_SPREAD: f32 = FRAC_PI_3;
+// keeps relocated enemies in bounded arenas off the walls
+const RELOCATION_BOUNDS_MARGIN: f32 = ENEMY_SIZE.x;
+
+const ENEMY_PROJECTILE_SIZE: Vec2 = Vec2::new(12.0, 12.0);
+const ENEMY_PROJECTILE_COLOR: Color = Color::CRIMSON;
+const ENEMY_PROJECTILE_RANGE: f32 = 900.0;
 
 pub struct EnemyPlugin;
 
@@ -22,21 +42,198 @@ impl Plugin for EnemyPlugin {
         app.add_systems(
             Update,
             (
-                move_towards_player_when_not_knockback,
+                move_towards_player_when_not_knockback.in_set(EnemyMovementSet),
                 die,
-                despawn_far_away_enemies,
+                relocate_far_away_enemies,
+                despawn_enemy_projectiles,
             )
                 .run_if(in_state(InGame)),
-        )
-        .add_systems(FixedUpdate, bullet_hit_enemy.run_if(in_state(InGame)));
+        );
     }
 }
 
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnemyMovementSet;
+
 #[derive(Component)]
 pub struct Enemy;
 
+// culled instead of relocated once the player leaves it behind
+#[derive(Component)]
+pub struct Disposable;
+
+#[derive(Component, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EnemyKind {
+    Grunt,
+    Splitter,
+    Bloater,
+    Plaguebearer,
+    Mender,
+    Charger,
+    Kiter,
+}
+
+struct EnemyStats {
+    size: Vec2,
+    color: Color,
+    health: f32,
+    move_speed: f32,
+    xp: f32,
+    // what spawning one costs out of the director's threat budget
+    threat: f32,
+}
+
+impl EnemyKind {
+    pub fn threat(self) -> f32 {
+        self.stats().threat
+    }
+
+    fn stats(self) -> EnemyStats {
+        match self {
+            EnemyKind::Grunt => EnemyStats {
+                size: ENEMY_SIZE,
+                color: Color::ORANGE_RED,
+                health: ENEMY_HEALTH,
+                move_speed: ENEMY_SPEED,
+                xp: 1.,
+                threat: 1.,
+            },
+            EnemyKind::Splitter => EnemyStats {
+                size: Vec2::new(60.0, 60.0),
+                color: Color::TOMATO,
+                health: 3.,
+                move_speed: 80.,
+                xp: 2.,
+                threat: 2.,
+            },
+            EnemyKind::Bloater => EnemyStats {
+                size: Vec2::new(65.0, 65.0),
+                color: Color::ORANGE,
+                health: 4.,
+                move_speed: 60.,
+                xp: 2.,
+                threat: 4.,
+            },
+            EnemyKind::Plaguebearer => EnemyStats {
+                size: ENEMY_SIZE,
+                color: Color::OLIVE,
+                health: 3.,
+                move_speed: 90.,
+                xp: 2.,
+                threat: 3.,
+            },
+            EnemyKind::Mender => EnemyStats {
+                size: Vec2::new(40.0, 40.0),
+                color: Color::SEA_GREEN,
+                health: 2.,
+                move_speed: 110.,
+                xp: 2.,
+                threat: 3.,
+            },
+            EnemyKind::Charger => EnemyStats {
+                size: Vec2::new(55.0, 45.0),
+                color: Color::INDIGO,
+                health: 3.,
+                move_speed: 90.,
+                xp: 3.,
+                threat: 3.,
+            },
+            EnemyKind::Kiter => EnemyStats {
+                size: Vec2::new(40.0, 40.0),
+                color: Color::TEAL,
+                health: 2.,
+                move_speed: 120.,
+                xp: 3.,
+                threat: 3.,
+            },
+        }
+    }
+
+    pub fn ai_behavior(self) -> Option<AiBehavior> {
+        match self {
+            EnemyKind::Charger => Some(AiBehavior::Charger {
+                aggro_range: 900.,
+                dash_range: 250.,
+                wind_up: Duration::from_millis(600),
+                dash: Duration::from_millis(400),
+                dash_speed: 650.,
+                cooldown: Duration::from_secs(1),
+            }),
+            EnemyKind::Kiter => Some(AiBehavior::Kiter {
+                aggro_range: 900.,
+                min_range: 200.,
+                max_range: 400.,
+                fire_interval: Duration::from_millis(1500),
+                projectile_speed: 300.,
+                flee_below_health: 0.5,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn death_behaviors(self) -> Vec<DeathBehavior> {
+        match self {
+            EnemyKind::Grunt | EnemyKind::Charger | EnemyKind::Kiter => {
+                vec![DeathBehavior::DropLoot]
+            }
+            EnemyKind::Splitter => vec![
+                DeathBehavior::DropLoot,
+                DeathBehavior::Split {
+                    kind: EnemyKind::Grunt,
+                    count: 2,
+                    scale: 0.6,
+                },
+            ],
+            EnemyKind::Bloater => vec![
+                DeathBehavior::DropLoot,
+                DeathBehavior::Explode {
+                    fuse: Duration::from_secs(1),
+                    radius: 100.,
+                    damage: 2.,
+                },
+            ],
+            EnemyKind::Plaguebearer => vec![
+                DeathBehavior::DropLoot,
+                DeathBehavior::PoisonPuddle {
+                    radius: 60.,
+                    damage: 1.,
+                    duration: Duration::from_secs(5),
+                },
+            ],
+            EnemyKind::Mender => vec![
+                DeathBehavior::DropLoot,
+                DeathBehavior::HealAllies {
+                    radius: 150.,
+                    amount: 2.,
+                },
+            ],
+        }
+    }
+}
+
 #[derive(Component)]
-struct XpValue(f32);
+pub struct XpValue(pub f32);
+
+#[derive(Component)]
+pub struct ContactDamage {
+    pub damage: f32,
+    pub cooldown: Timer,
+}
+
+impl ContactDamage {
+    pub fn new(damage: f32, cooldown: Duration) -> Self {
+        let mut cooldown = Timer::new(cooldown, TimerMode::Once);
+        // ready to hit as soon as it touches the player
+        cooldown.tick(cooldown.duration());
+        Self { damage, cooldown }
+    }
+}
+
+#[derive(Component)]
+pub struct EnemyProjectile {
+    spawn_location: Vec2,
+    pub damage: f32,
+}
 
 #[derive(Bundle)]
 pub struct EnemyBundle {
@@ -47,6 +244,11 @@ pub struct EnemyBundle {
     velocity: Velocity,
     movable: Movable,
     xp_value: XpValue,
+    contact_damage: ContactDamage,
+    kind: EnemyKind,
+    on_death: OnDeath,
+    shootable: Shootable,
+    blocked_by_obstacles: BlockedByObstacles,
 }
 
 impl Default for EnemyBundle {
@@ -67,116 +269,187 @@ impl Default for EnemyBundle {
                 max: ENEMY_HEALTH,
             },
             velocity: Velocity(Vec2::ZERO),
-            movable: Movable { move_speed: 100. },
+            movable: Movable {
+                move_speed: ENEMY_SPEED,
+            },
             xp_value: XpValue(1.),
+            contact_damage: ContactDamage::new(ENEMY_CONTACT_DAMAGE, ENEMY_ATTACK_COOLDOWN),
+            kind: EnemyKind::Grunt,
+            on_death: OnDeath(EnemyKind::Grunt.death_behaviors()),
+            shootable: Shootable,
+            blocked_by_obstacles: BlockedByObstacles,
         }
     }
 }
 
-pub fn prepare_enemy(location: &Vec2) -> EnemyBundle {
+impl EnemyBundle {
+    pub fn scaled(mut self, scale: f32) -> Self {
+        let size = self.collider.0 * scale;
+        self.sprite.sprite.custom_size = Some(size);
+        self.collider = Collider(size);
+        self.health.max = (self.health.max * scale).ceil();
+        self.health.current = self.health.max;
+        self.xp_value.0 *= scale;
+        self
+    }
+}
+
+pub fn prepare_enemy(kind: EnemyKind, location: &Vec2) -> EnemyBundle {
+    let stats = kind.stats();
     EnemyBundle {
         sprite: SpriteBundle {
             transform: Transform::from_xyz(location.x, location.y, 0.),
             sprite: Sprite {
-                color: Color::ORANGE_RED,
-                custom_size: Some(ENEMY_SIZE),
+                color: stats.color,
+                custom_size: Some(stats.size),
                 ..default()
             },
             ..default()
         },
-        collider: Collider(ENEMY_SIZE),
+        collider: Collider(stats.size),
         enemy: Enemy,
         health: Health {
-            current: ENEMY_HEALTH,
-            max: ENEMY_HEALTH,
+            current: stats.health,
+            max: stats.health,
         },
         velocity: Velocity(Vec2::ZERO),
-        movable: Movable { move_speed: 100. },
-        xp_value: XpValue(1.),
+        movable: Movable {
+            move_speed: stats.move_speed,
+        },
+        xp_value: XpValue(stats.xp),
+        contact_damage: ContactDamage::new(ENEMY_CONTACT_DAMAGE, ENEMY_ATTACK_COOLDOWN),
+        kind,
+        on_death: OnDeath(kind.death_behaviors()),
+        shootable: Shootable,
+        blocked_by_obstacles: BlockedByObstacles,
     }
 }
 
-fn bullet_hit_enemy(
-    mut commands: Commands,
-    q_bullet: Query<(&Transform, Entity, &Collider, &Velocity), With<Bullet>>,
-    mut q_enemy: Query<(&Transform, &Collider, &mut Health, Entity), With<Enemy>>,
-) {
-    for (bullet_transform, bullet_entity, bullet_collider, velocity) in q_bullet.iter() {
-        for (enemy_transform, enemy_collider, mut health, entity) in q_enemy.iter_mut() {
-            if let Some(_) = collide(
-                bullet_transform.translation,
-                bullet_collider.0,
-                enemy_transform.translation,
-                enemy_collider.0,
-            ) {
-                commands.entity(bullet_entity).despawn();
-                health.current -= 1.;
-                let knockback = Knockback {
-                    velocity: velocity.normalize() * 20.,
-                    start_position: enemy_transform.translation.truncate(),
-                    distance: 10.,
-                };
-
-                commands.entity(entity).insert(knockback);
-            }
-        }
-    }
+pub fn prepare_enemy_projectile(location: Vec2, velocity: Vec2) -> impl Bundle {
+    (
+        SpriteBundle {
+            transform: Transform::from_xyz(location.x, location.y, 0.),
+            sprite: Sprite {
+                color: ENEMY_PROJECTILE_COLOR,
+                custom_size: Some(ENEMY_PROJECTILE_SIZE),
+                ..default()
+            },
+            ..default()
+        },
+        Collider(ENEMY_PROJECTILE_SIZE),
+        Velocity(velocity),
+        EnemyProjectile {
+            spawn_location: location,
+            damage: 1.,
+        },
+    )
 }
 
-fn move_towards_player_when_not_knockback(
-    mut q_enemy: Query<(&Transform, &mut Velocity, &Movable), (With<Enemy>, Without<Knockback>)>,
+pub fn move_towards_player_when_not_knockback(
+    mut q_enemy: Query<
+        (&Transform, &mut Velocity, &Movable),
+        (With<Enemy>, Without<Knockback>, Without<AiBrain>),
+    >,
     q_player: Query<&Transform, With<Player>>,
+    flow_field: Res<FlowField>,
 ) {
     for (enemy_transform, mut velocity, movable) in q_enemy.iter_mut() {
         let player_transform = q_player.single();
-        let direction = (player_transform.translation.truncate()
-            - enemy_transform.translation.truncate())
-        .normalize();
+        let enemy_position = enemy_transform.translation.truncate();
+        let direction = flow_field.direction_at(enemy_position).unwrap_or_else(|| {
+            (player_transform.translation.truncate() - enemy_position).normalize_or_zero()
+        });
         velocity.0 = direction * movable.move_speed;
     }
 }
 
-fn die(
+pub fn die(
     mut commands: Commands,
-    q_enemy: Query<(Entity, &Health, &Transform, &XpValue), With<Enemy>>,
+    q_enemy: Query<
+        (
+            Entity,
+            &Health,
+            &Transform,
+            &XpValue,
+            &EnemyKind,
+            &OnDeath,
+            Has<Elite>,
+            Option<&Explosive>,
+            Option<&Splitting>,
+        ),
+        (With<Enemy>, Without<Boss>),
+    >,
     mut score: ResMut<Score>,
+    mut ev_enemy_died: EventWriter<EnemyDied>,
 ) {
-    for (entity, health, transform, xp) in q_enemy.iter() {
+    for (entity, health, transform, xp, kind, on_death, elite, explosive, splitting) in
+        q_enemy.iter()
+    {
         if health.current <= 0. {
-            drop_on_dead(&mut commands, transform.translation, xp);
+            let mut behaviors = on_death.0.clone();
+            behaviors.extend(explosive.map(Explosive::death_behavior));
+            behaviors.extend(splitting.map(Splitting::death_behavior));
+            ev_enemy_died.send(EnemyDied {
+                position: transform.translation.truncate(),
+                kind: *kind,
+                xp: xp.0,
+                elite,
+                behaviors,
+            });
             commands.entity(entity).despawn();
             score.0 += 1;
         }
     }
 }
 
-fn despawn_far_away_enemies(
+fn relocate_far_away_enemies(
     mut commands: Commands,
-    q_player_transform: Query<&Transform, With<Player>>,
-    q_enemy: Query<(&Transform, Entity), With<Enemy>>,
+    q_player: Query<(&Transform, &Velocity), With<Player>>,
+    mut q_enemy: Query<
+        (&mut Transform, Entity, Has<Disposable>),
+        (With<Enemy>, Without<Player>, Without<Boss>),
+    >,
+    bounds: Option<Res<ArenaBounds>>,
+    mut rng: ResMut<SpawnRng>,
 ) {
-    let player_position = q_player_transform.single().translation.truncate();
-    for (transform, entity) in q_enemy.iter() {
-        if (transform.translation.truncate() - player_position).length() > MAX_ENEMY_DISTANCE {
+    let (player_transform, player_velocity) = q_player.single();
+    let player_position = player_transform.translation.truncate();
+    let rng = &mut rng.0;
+    for (mut transform, entity, disposable) in q_enemy.iter_mut() {
+        if (transform.translation.truncate() - player_position).length() <= MAX_ENEMY_DISTANCE {
+            continue;
+        }
+        if disposable {
             commands.entity(entity).despawn();
+            continue;
         }
-    }
-}
 
-fn drop_on_dead(commands: &mut Commands, position: Vec3, xp: &XpValue) {
-    let mut rng = thread_rng();
-    let gem_size = vec2(10., 10.);
-    if rng.gen_range(0..10) > 5 {
-        let drop = SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(gem_size),
-                color: Color::PINK,
-                ..default()
-            },
-            transform: Transform::from_translation(position),
-            ..default()
+        // move the enemy back in front of the player, where it will be run into again
+        let heading = if player_velocity.0 == Vec2::ZERO {
+            rng.gen_range(0.0..TAU)
+        } else {
+            player_velocity.y.atan2(player_velocity.x)
         };
+        let angle = heading + rng.gen_range(-RELOCATION_SPREAD..=RELOCATION_SPREAD);
+        let mut location = player_position + Vec2::from_angle(angle) * RELOCATION_DISTANCE;
+        if let Some(bounds) = bounds.as_deref() {
+            location = bounds.keep_inside(location, RELOCATION_BOUNDS_MARGIN, rng);
+        }
+        transform.translation.x = location.x;
+        transform.translation.y = location.y;
+        commands.entity(entity).remove::<Knockback>();
+    }
+}
 
-        commands.spawn((drop, Collider(gem_size), XpGem(xp.0)));
+fn despawn_enemy_projectiles(
+    mut commands: Commands,
+    q_projectile: Query<(&Transform, Entity, &EnemyProjectile)>,
+) {
+    for (transform, entity, projectile) in q_projectile.iter() {
+        if (projectile.spawn_location - transform.translation.truncate()).length()
+            > ENEMY_PROJECTILE_RANGE
+        {
+            commands.entity(entity).despawn();
+        }
     }
 }
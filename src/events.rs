@@ -8,3 +8,10 @@ pub struct PlayerHealthChanged {
 
 #[derive(Event)]
 pub struct PlayerDies;
+
+// read in FixedUpdate, so it has to be sent from there too, before PlayerDamageSet,
+// or it can be cleared on a frame without a fixed step
+#[derive(Event)]
+pub struct DamagePlayer {
+    pub amount: f32,
+}
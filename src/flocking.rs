@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+use crate::components::{Knockback, Movable, Velocity};
+use crate::enemy::{Enemy, EnemyMovementSet};
+use crate::resources::AppState::InGame;
+use crate::spatial_grid::{update_enemy_grid, SpatialGrid};
+
+pub struct FlockingPlugin;
+
+impl Plugin for FlockingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlockingConfig>().add_systems(
+            Update,
+            apply_flocking
+                .after(update_enemy_grid)
+                .after(EnemyMovementSet)
+                .run_if(in_state(InGame)),
+        );
+    }
+}
+
+#[derive(Resource)]
+pub struct FlockingConfig {
+    pub neighbour_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: Option<f32>,
+    pub cohesion_weight: Option<f32>,
+}
+
+impl Default for FlockingConfig {
+    fn default() -> Self {
+        Self {
+            neighbour_radius: 60.0,
+            separation_weight: 1.5,
+            alignment_weight: None,
+            cohesion_weight: None,
+        }
+    }
+}
+
+fn apply_flocking(
+    config: Res<FlockingConfig>,
+    grid: Res<SpatialGrid>,
+    mut q_enemy: Query<
+        (Entity, &Transform, &mut Velocity, &Movable),
+        (With<Enemy>, Without<Knockback>),
+    >,
+) {
+    for (entity, transform, mut velocity, movable) in q_enemy.iter_mut() {
+        let position = transform.translation.truncate();
+        let steering = flocking_steering(&config, &grid, entity, position);
+        // never slow down anything that is already moving faster, like a dash
+        let max_speed = velocity.length().max(movable.move_speed);
+        velocity.0 = (velocity.0 + steering * movable.move_speed).clamp_length_max(max_speed);
+    }
+}
+
+fn flocking_steering(
+    config: &FlockingConfig,
+    grid: &SpatialGrid,
+    entity: Entity,
+    position: Vec2,
+) -> Vec2 {
+    let mut separation = Vec2::ZERO;
+    let mut heading = Vec2::ZERO;
+    let mut centre = Vec2::ZERO;
+    let mut neighbours = 0;
+
+    for other in grid.within(position, config.neighbour_radius) {
+        if other.entity == entity {
+            continue;
+        }
+        let offset = position - other.position;
+        let distance = offset.length();
+        // stacked exactly on top of each other, pick a direction based on the entity
+        let away = if distance > f32::EPSILON {
+            offset / distance
+        } else {
+            Vec2::from_angle(entity.index() as f32)
+        };
+        separation += away * (1.0 - distance / config.neighbour_radius);
+        heading += other.velocity.normalize_or_zero();
+        centre += other.position;
+        neighbours += 1;
+    }
+
+    if neighbours == 0 {
+        return Vec2::ZERO;
+    }
+
+    let mut steering = separation * config.separation_weight;
+    if let Some(weight) = config.alignment_weight {
+        steering += (heading / neighbours as f32).normalize_or_zero() * weight;
+    }
+    if let Some(weight) = config.cohesion_weight {
+        steering += (centre / neighbours as f32 - position).normalize_or_zero() * weight;
+    }
+    steering
+}
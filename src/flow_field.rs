@@ -0,0 +1,407 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::arena::Arena;
+use crate::map::{
+    chunk_tiles, tile_to_world_pos, world_pos_to_tile, ChunkEvent, ChunkManager, TileOccupancy,
+};
+use crate::player::Player;
+use crate::resources::AppState::InGame;
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+// in tiles, how far the player gets from the field's target before it's moved
+const RETARGET_DISTANCE: f32 = 2.0;
+// tiles a moved target's field settles per frame, the old field is used until it's done
+const RETARGET_TILES_PER_FRAME: usize = 2048;
+
+pub struct FlowFieldPlugin;
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlowField>()
+            .add_systems(OnEnter(InGame), reset_flow_field)
+            .add_systems(Update, update_flow_field.run_if(in_state(InGame)));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct FlowField {
+    target: Option<IVec2>,
+    // loaded tiles nothing solid stands on, the only ones the field covers
+    walkable: HashSet<IVec2>,
+    costs: HashMap<IVec2, u32>,
+    pending: Option<PendingField>,
+}
+
+// the field around a moved target, filled in breadth first over several frames
+struct PendingField {
+    target: IVec2,
+    costs: HashMap<IVec2, u32>,
+    frontier: VecDeque<IVec2>,
+}
+
+impl PendingField {
+    fn new(target: IVec2, walkable: &HashSet<IVec2>) -> Self {
+        let mut costs = HashMap::new();
+        let mut frontier = VecDeque::new();
+        if walkable.contains(&target) {
+            costs.insert(target, 0);
+            frontier.push_back(target);
+        }
+        Self {
+            target,
+            costs,
+            frontier,
+        }
+    }
+}
+
+impl FlowField {
+    // every tile points at its cheapest neighbour, without cutting blocked corners
+    pub fn direction_at(&self, world_pos: Vec2) -> Option<Vec2> {
+        let tile = world_pos_to_tile(world_pos);
+        let cost = *self.costs.get(&tile)?;
+        let mut best = (cost, IVec2::ZERO);
+        for offset in NEIGHBOURS {
+            let Some(&next_cost) = self.costs.get(&(tile + offset)) else {
+                continue;
+            };
+            let diagonal = offset.x != 0 && offset.y != 0;
+            if diagonal
+                && (!self.costs.contains_key(&(tile + IVec2::new(offset.x, 0)))
+                    || !self.costs.contains_key(&(tile + IVec2::new(0, offset.y))))
+            {
+                continue;
+            }
+            if next_cost < best.0 {
+                best = (next_cost, offset);
+            }
+        }
+        (best.1 != IVec2::ZERO)
+            .then(|| (tile_to_world_pos(tile + best.1) - tile_to_world_pos(tile)).normalize())
+    }
+
+    pub fn rebuild(&mut self, target: IVec2, walkable: HashSet<IVec2>) {
+        self.walkable = walkable;
+        self.retarget(target);
+        self.advance(usize::MAX);
+    }
+
+    // every cost depends on the target, so moving it starts a new field,
+    // which only replaces the current one once `advance` has finished it
+    pub fn retarget(&mut self, target: IVec2) {
+        self.pending = Some(PendingField::new(target, &self.walkable));
+    }
+
+    pub fn advance(&mut self, budget: usize) {
+        let Some(pending) = self.pending.as_mut() else {
+            return;
+        };
+        for _ in 0..budget {
+            let Some(tile) = pending.frontier.pop_front() else {
+                break;
+            };
+            let cost = pending.costs[&tile];
+            for offset in &NEIGHBOURS[..4] {
+                let next = tile + *offset;
+                if self.walkable.contains(&next) && !pending.costs.contains_key(&next) {
+                    pending.costs.insert(next, cost + 1);
+                    pending.frontier.push_back(next);
+                }
+            }
+        }
+        if !pending.frontier.is_empty() {
+            return;
+        }
+        if let Some(PendingField { target, costs, .. }) = self.pending.take() {
+            self.target = Some(target);
+            self.costs = costs;
+        }
+    }
+
+    fn pending_target(&self) -> Option<IVec2> {
+        self.pending.as_ref().map(|pending| pending.target)
+    }
+
+    // a half built field may already have spread over the changed tiles
+    fn restart_pending(&mut self) {
+        if let Some(target) = self.pending_target() {
+            self.retarget(target);
+        }
+    }
+
+    // newly walkable tiles only ever shorten paths, so costs spread out from their edge
+    pub fn add_tiles(&mut self, tiles: impl IntoIterator<Item = IVec2>) {
+        let mut seeds = Vec::new();
+        for tile in tiles {
+            if !self.walkable.insert(tile) {
+                continue;
+            }
+            if self.target == Some(tile) {
+                self.costs.insert(tile, 0);
+            }
+            seeds.extend(
+                self.cardinal_neighbours(tile)
+                    .filter(|next| self.costs.contains_key(next)),
+            );
+            seeds.extend(self.costs.contains_key(&tile).then_some(tile));
+        }
+        self.lower(seeds);
+        self.restart_pending();
+    }
+
+    // tiles whose shortest path ran through a removed tile lose their cost,
+    // then get it back from whatever still reaches the target around the gap
+    pub fn remove_tiles(&mut self, tiles: impl IntoIterator<Item = IVec2>) {
+        let mut orphans = Vec::new();
+        for tile in tiles {
+            if !self.walkable.remove(&tile) {
+                continue;
+            }
+            if let Some(cost) = self.costs.remove(&tile) {
+                orphans.push((tile, cost));
+            }
+        }
+
+        let mut unsupported = Vec::new();
+        while let Some((tile, cost)) = orphans.pop() {
+            unsupported.push(tile);
+            for next in NEIGHBOURS[..4].iter().map(|offset| tile + *offset) {
+                let Some(&next_cost) = self.costs.get(&next) else {
+                    continue;
+                };
+                // a tile keeps its cost as long as one neighbour is a step closer
+                if next_cost != cost + 1 || self.has_support(next, next_cost) {
+                    continue;
+                }
+                self.costs.remove(&next);
+                orphans.push((next, next_cost));
+            }
+        }
+
+        let seeds: Vec<IVec2> = unsupported
+            .iter()
+            .flat_map(|tile| self.cardinal_neighbours(*tile))
+            .filter(|next| self.costs.contains_key(next))
+            .collect();
+        self.lower(seeds);
+        self.restart_pending();
+    }
+
+    fn cardinal_neighbours(&self, tile: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        NEIGHBOURS[..4]
+            .iter()
+            .map(move |offset| tile + *offset)
+            .filter(|next| self.walkable.contains(next))
+    }
+
+    fn has_support(&self, tile: IVec2, cost: u32) -> bool {
+        let closer = cost.wrapping_sub(1);
+        self.target == Some(tile)
+            || NEIGHBOURS[..4]
+                .iter()
+                .any(|offset| self.costs.get(&(tile + *offset)) == Some(&closer))
+    }
+
+    // dijkstra outwards from tiles that already have their final cost
+    fn lower(&mut self, seeds: Vec<IVec2>) {
+        let mut frontier: BinaryHeap<_> = seeds
+            .into_iter()
+            .map(|tile| Reverse((self.costs[&tile], tile.x, tile.y)))
+            .collect();
+        while let Some(Reverse((cost, x, y))) = frontier.pop() {
+            let tile = IVec2::new(x, y);
+            if self.costs.get(&tile) != Some(&cost) {
+                continue;
+            }
+            for offset in &NEIGHBOURS[..4] {
+                let next = tile + *offset;
+                if !self.walkable.contains(&next)
+                    || self
+                        .costs
+                        .get(&next)
+                        .is_some_and(|next_cost| *next_cost <= cost + 1)
+                {
+                    continue;
+                }
+                self.costs.insert(next, cost + 1);
+                frontier.push(Reverse((cost + 1, next.x, next.y)));
+            }
+        }
+    }
+}
+
+fn reset_flow_field(mut flow_field: ResMut<FlowField>) {
+    *flow_field = FlowField::default();
+}
+
+fn update_flow_field(
+    mut flow_field: ResMut<FlowField>,
+    mut ev_chunk: EventReader<ChunkEvent>,
+    chunk_manager: Res<ChunkManager>,
+    occupancy: Res<TileOccupancy>,
+    arena: Option<Res<Arena>>,
+    q_player: Query<&Transform, With<Player>>,
+) {
+    let player_tile = world_pos_to_tile(q_player.single().translation.truncate());
+    let walkable_in_chunk =
+        |chunk_pos: IVec2| chunk_tiles(chunk_pos).filter(|tile| !occupancy.is_solid(*tile));
+
+    // built from scratch once per run, after that only the tiles that stream in or out change
+    let arena_changed = arena.is_some() && occupancy.is_changed();
+    if flow_field.target.is_none() || arena_changed {
+        ev_chunk.clear();
+        let walkable: HashSet<IVec2> = match &arena {
+            Some(arena) => arena
+                .tiles()
+                .filter(|tile| !occupancy.is_solid(*tile))
+                .collect(),
+            None => chunk_manager
+                .spawned_chunks
+                .keys()
+                .flat_map(|chunk_pos| walkable_in_chunk(*chunk_pos))
+                .collect(),
+        };
+        flow_field.rebuild(player_tile, walkable);
+        return;
+    }
+
+    for ev in ev_chunk.read() {
+        match *ev {
+            ChunkEvent::Loaded(chunk_pos) => flow_field.add_tiles(walkable_in_chunk(chunk_pos)),
+            ChunkEvent::Unloaded(chunk_pos) => flow_field.remove_tiles(chunk_tiles(chunk_pos)),
+        }
+    }
+    // close to the target enemies have no direction and walk straight at the player anyway
+    let target = flow_field
+        .pending_target()
+        .or(flow_field.target)
+        .unwrap_or(player_tile);
+    if target.as_vec2().distance(player_tile.as_vec2()) > RETARGET_DISTANCE {
+        flow_field.retarget(player_tile);
+    }
+    flow_field.advance(RETARGET_TILES_PER_FRAME);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_area(size: i32) -> HashSet<IVec2> {
+        (0..size)
+            .flat_map(|y| (0..size).map(move |x| IVec2::new(x, y)))
+            .collect()
+    }
+
+    fn assert_same_field(incremental: &FlowField, target: IVec2, walkable: HashSet<IVec2>) {
+        let mut full = FlowField::default();
+        full.rebuild(target, walkable);
+        assert_eq!(incremental.costs, full.costs);
+    }
+
+    fn walled_area() -> HashSet<IVec2> {
+        let mut walkable = open_area(12);
+        for y in 0..10 {
+            walkable.remove(&IVec2::new(5, y));
+        }
+        walkable
+    }
+
+    #[test]
+    fn added_tiles_match_a_full_rebuild() {
+        let mut field = FlowField::default();
+        field.rebuild(IVec2::new(2, 2), open_area(8));
+        let extra: Vec<IVec2> = (8..12)
+            .flat_map(|x| (0..8).map(move |y| IVec2::new(x, y)))
+            .collect();
+        field.add_tiles(extra.iter().copied());
+
+        let mut walkable = open_area(8);
+        walkable.extend(extra);
+        assert_same_field(&field, IVec2::new(2, 2), walkable);
+        assert_eq!(field.costs[&IVec2::new(11, 7)], 14);
+    }
+
+    #[test]
+    fn removed_tiles_route_around_the_gap() {
+        let mut field = FlowField::default();
+        field.rebuild(IVec2::new(0, 0), open_area(8));
+        let wall: Vec<IVec2> = (0..7).map(|y| IVec2::new(3, y)).collect();
+        field.remove_tiles(wall.iter().copied());
+
+        let mut walkable = open_area(8);
+        for tile in &wall {
+            walkable.remove(tile);
+        }
+        assert_same_field(&field, IVec2::new(0, 0), walkable);
+        // around the top of the wall instead of through it
+        assert_eq!(field.costs[&IVec2::new(4, 0)], 18);
+        assert!(field
+            .direction_at(tile_to_world_pos(IVec2::new(3, 0)))
+            .is_none());
+    }
+
+    #[test]
+    fn cut_off_tiles_lose_their_direction() {
+        let mut field = FlowField::default();
+        field.rebuild(IVec2::new(0, 0), open_area(8));
+        field.remove_tiles((0..8).map(|y| IVec2::new(3, y)));
+
+        assert!(!field.costs.contains_key(&IVec2::new(5, 5)));
+        assert!(field
+            .direction_at(tile_to_world_pos(IVec2::new(5, 5)))
+            .is_none());
+        field.add_tiles([IVec2::new(3, 4)]);
+        assert_eq!(field.costs[&IVec2::new(5, 5)], 10);
+    }
+
+    #[test]
+    fn retargeting_matches_a_full_rebuild() {
+        let mut field = FlowField::default();
+        field.rebuild(IVec2::new(0, 0), walled_area());
+        field.retarget(IVec2::new(9, 2));
+
+        let mut steps = 0;
+        while field.pending.is_some() {
+            // the old field keeps steering until the new one is done
+            assert_eq!(field.costs[&IVec2::new(0, 0)], 0);
+            field.advance(16);
+            steps += 1;
+        }
+        assert!(steps > 1);
+        assert_eq!(field.target, Some(IVec2::new(9, 2)));
+        assert_same_field(&field, IVec2::new(9, 2), walled_area());
+    }
+
+    #[test]
+    fn tiles_changed_while_retargeting_end_up_in_the_new_field() {
+        let mut field = FlowField::default();
+        field.rebuild(IVec2::new(0, 0), walled_area());
+        field.retarget(IVec2::new(9, 2));
+        field.advance(40);
+        field.remove_tiles((6..12).map(|x| IVec2::new(x, 5)));
+        field.add_tiles([IVec2::new(5, 4)]);
+        field.advance(usize::MAX);
+
+        let mut walkable = walled_area();
+        for x in 6..12 {
+            walkable.remove(&IVec2::new(x, 5));
+        }
+        walkable.insert(IVec2::new(5, 4));
+        assert_same_field(&field, IVec2::new(9, 2), walkable);
+    }
+}
@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::components::{Health, Movable, Velocity};
+use crate::enemy::Enemy;
+use crate::events::{DamagePlayer, PlayerHealthChanged};
+use crate::map::{chunk_tiles, tile_to_world_pos, ChunkEvent, TILE_SIZE};
+use crate::player::{Player, PlayerDamageSet};
+use crate::resources::AppState::InGame;
+use crate::seed::RunSeed;
+use crate::systems::move_all;
+use crate::terrain::{hazard_at, HazardKind};
+
+// above the terrain and scenery, below everything that moves
+const HAZARD_Z: f32 = -4.0;
+const HAZARD_TICK: Duration = Duration::from_millis(500);
+const LAVA_TICK_DAMAGE: f32 = 1.0;
+const SPRING_TICK_HEAL: f32 = 0.5;
+pub const MUD_SPEED_MULTIPLIER: f32 = 0.5;
+// how quickly velocity on ice catches up with where something wants to go, per second
+const ICE_GRIP: f32 = 2.5;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AreaEvent>()
+            .add_systems(
+                Update,
+                (follow_chunks, track_occupants, handle_enter_exit)
+                    .chain()
+                    .run_if(in_state(InGame)),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    slide_on_ice.before(move_all),
+                    tick_area_effects.before(PlayerDamageSet),
+                )
+                    .run_if(in_state(InGame)),
+            );
+    }
+}
+
+// a zone that does something to whoever stands in it
+#[derive(Component)]
+pub struct AreaEffect {
+    pub kind: HazardKind,
+    pub size: Vec2,
+    tick: Timer,
+    occupants: HashSet<Entity>,
+}
+
+impl AreaEffect {
+    pub fn new(kind: HazardKind, size: Vec2) -> Self {
+        Self {
+            kind,
+            size,
+            tick: Timer::new(HAZARD_TICK, TimerMode::Repeating),
+            occupants: HashSet::new(),
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub enum AreaEvent {
+    Entered { entity: Entity, kind: HazardKind },
+    Exited { entity: Entity, kind: HazardKind },
+}
+
+// generated zones live and die with their chunk
+#[derive(Component)]
+struct ChunkHazard {
+    chunk: IVec2,
+}
+
+// counts zones, since overlapping mud should only slow once
+#[derive(Component)]
+pub struct Mired {
+    zones: u32,
+}
+
+#[derive(Component)]
+struct Slippery {
+    zones: u32,
+    momentum: Vec2,
+}
+
+pub fn spawn_area_effect(commands: &mut Commands, kind: HazardKind, area: Rect) -> Entity {
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(area.size()),
+                    ..default()
+                },
+                transform: Transform::from_translation(area.center().extend(HAZARD_Z)),
+                ..default()
+            },
+            AreaEffect::new(kind, area.size()),
+        ))
+        .id()
+}
+
+fn follow_chunks(
+    mut commands: Commands,
+    mut ev_chunk: EventReader<ChunkEvent>,
+    run_seed: Res<RunSeed>,
+    q_hazard: Query<(Entity, &ChunkHazard, &AreaEffect)>,
+    mut ev_area: EventWriter<AreaEvent>,
+) {
+    let seed = run_seed.terrain_seed();
+    let tile_size = Vec2::new(TILE_SIZE.x, TILE_SIZE.y);
+    for ev in ev_chunk.read() {
+        match *ev {
+            ChunkEvent::Unloaded(chunk_pos) => {
+                for (entity, hazard, area) in q_hazard.iter() {
+                    if hazard.chunk != chunk_pos {
+                        continue;
+                    }
+                    // nobody keeps a status from a zone that's gone
+                    ev_area.send_batch(area.occupants.iter().map(|occupant| AreaEvent::Exited {
+                        entity: *occupant,
+                        kind: area.kind,
+                    }));
+                    commands.entity(entity).despawn();
+                }
+            }
+            ChunkEvent::Loaded(chunk_pos) => {
+                for tile in chunk_tiles(chunk_pos) {
+                    let Some((kind, size)) = hazard_at(tile, seed) else {
+                        continue;
+                    };
+                    let min = tile_to_world_pos(tile) - tile_size / 2.;
+                    let area = Rect::from_corners(min, min + size.as_vec2() * tile_size);
+                    let entity = spawn_area_effect(&mut commands, kind, area);
+                    commands
+                        .entity(entity)
+                        .insert(ChunkHazard { chunk: chunk_pos });
+                }
+            }
+        }
+    }
+}
+
+fn track_occupants(
+    mut q_area: Query<(&Transform, &mut AreaEffect)>,
+    q_target: Query<(Entity, &Transform), Or<(With<Player>, With<Enemy>)>>,
+    mut ev_area: EventWriter<AreaEvent>,
+) {
+    for (area_transform, mut area) in q_area.iter_mut() {
+        let bounds = Rect::from_center_size(area_transform.translation.truncate(), area.size);
+        let inside: HashSet<Entity> = q_target
+            .iter()
+            .filter(|(_, transform)| bounds.contains(transform.translation.truncate()))
+            .map(|(entity, _)| entity)
+            .collect();
+
+        let kind = area.kind;
+        for entity in inside.difference(&area.occupants) {
+            ev_area.send(AreaEvent::Entered {
+                entity: *entity,
+                kind,
+            });
+        }
+        // despawned entities leave too, whoever handles it just won't find them
+        for entity in area.occupants.difference(&inside) {
+            ev_area.send(AreaEvent::Exited {
+                entity: *entity,
+                kind,
+            });
+        }
+        area.occupants = inside;
+    }
+}
+
+fn handle_enter_exit(
+    mut commands: Commands,
+    mut ev_area: EventReader<AreaEvent>,
+    // bosses have no Movable and read Mired themselves
+    mut q_target: Query<(
+        Option<&mut Movable>,
+        &Velocity,
+        Option<&mut Mired>,
+        Option<&mut Slippery>,
+    )>,
+) {
+    // net change per entity first, components inserted here only show up next frame
+    let mut changes: HashMap<(Entity, HazardKind), i32> = HashMap::new();
+    for ev in ev_area.read() {
+        match *ev {
+            AreaEvent::Entered { entity, kind } => *changes.entry((entity, kind)).or_default() += 1,
+            AreaEvent::Exited { entity, kind } => *changes.entry((entity, kind)).or_default() -= 1,
+        }
+    }
+
+    for ((entity, kind), change) in changes {
+        let Ok((movable, velocity, mired, slippery)) = q_target.get_mut(entity) else {
+            continue;
+        };
+        match kind {
+            HazardKind::Mud => {
+                let zones = mired.as_ref().map_or(0, |mired| mired.zones);
+                let zones = (zones as i32 + change).max(0) as u32;
+                match (mired, zones) {
+                    (None, 0) => {}
+                    (None, _) => {
+                        if let Some(mut movable) = movable {
+                            movable.move_speed *= MUD_SPEED_MULTIPLIER;
+                        }
+                        commands.entity(entity).insert(Mired { zones });
+                    }
+                    (Some(_), 0) => {
+                        if let Some(mut movable) = movable {
+                            movable.move_speed /= MUD_SPEED_MULTIPLIER;
+                        }
+                        commands.entity(entity).remove::<Mired>();
+                    }
+                    (Some(mut mired), _) => mired.zones = zones,
+                }
+            }
+            HazardKind::Ice => {
+                let zones = slippery.as_ref().map_or(0, |slippery| slippery.zones);
+                let zones = (zones as i32 + change).max(0) as u32;
+                match (slippery, zones) {
+                    (None, 0) => {}
+                    (None, _) => {
+                        commands.entity(entity).insert(Slippery {
+                            zones,
+                            momentum: velocity.0,
+                        });
+                    }
+                    (Some(_), 0) => {
+                        commands.entity(entity).remove::<Slippery>();
+                    }
+                    (Some(mut slippery), _) => slippery.zones = zones,
+                }
+            }
+            // lava and springs only act while something stands in them
+            HazardKind::Lava | HazardKind::Spring => {}
+        }
+    }
+}
+
+fn tick_area_effects(
+    time: Res<Time>,
+    mut q_area: Query<&mut AreaEffect>,
+    mut q_health: Query<(&mut Health, Has<Player>)>,
+    mut ev_damage_player: EventWriter<DamagePlayer>,
+    mut ev_player_health: EventWriter<PlayerHealthChanged>,
+) {
+    for mut area in q_area.iter_mut() {
+        area.tick.tick(time.delta());
+        if !area.tick.just_finished() {
+            continue;
+        }
+        for occupant in area.occupants.iter() {
+            let Ok((mut health, is_player)) = q_health.get_mut(*occupant) else {
+                continue;
+            };
+            match area.kind {
+                // the player takes it as regular damage, invulnerability and all
+                HazardKind::Lava if is_player => ev_damage_player.send(DamagePlayer {
+                    amount: LAVA_TICK_DAMAGE,
+                }),
+                HazardKind::Lava => health.current -= LAVA_TICK_DAMAGE,
+                HazardKind::Spring => {
+                    if health.current >= health.max {
+                        continue;
+                    }
+                    health.current = (health.current + SPRING_TICK_HEAL).min(health.max);
+                    if is_player {
+                        ev_player_health.send(PlayerHealthChanged {
+                            current: health.current,
+                            max: health.max,
+                        });
+                    }
+                }
+                HazardKind::Mud | HazardKind::Ice => {}
+            }
+        }
+    }
+}
+
+// on ice velocity only drifts towards what movement asks for
+fn slide_on_ice(mut q_slippery: Query<(&mut Velocity, &mut Slippery)>, time: Res<Time<Fixed>>) {
+    let grip = (ICE_GRIP * time.delta().as_secs_f32()).min(1.);
+    for (mut velocity, mut slippery) in q_slippery.iter_mut() {
+        slippery.momentum = slippery.momentum.lerp(velocity.0, grip);
+        velocity.0 = slippery.momentum;
+    }
+}
@@ -0,0 +1,408 @@
+use std::f32::consts::TAU;
+use std::ops::RangeInclusive;
+
+use bevy::{prelude::*, utils::HashMap};
+use rand::prelude::*;
+use serde::Deserialize;
+
+use crate::components::{Chest, Collider, XpGem};
+use crate::data::load_ron;
+use crate::enemy::EnemyKind;
+use crate::terrain::PropKind;
+
+const LOOT_TABLES_PATH: &str = "loot_tables.ron";
+const MAX_TABLE_DEPTH: u32 = 8;
+
+const GEM_SIZE: Vec2 = Vec2::new(10.0, 10.0);
+const CHEST_SIZE: Vec2 = Vec2::new(30.0, 30.0);
+const DROP_SCATTER: f32 = 15.0;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LootTables::load(LOOT_TABLES_PATH));
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum LootItem {
+    // worth the xp of whatever dropped it, times the multiplier
+    XpGem { multiplier: f32 },
+    Chest { xp: f32 },
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum LootCondition {
+    MinPlayerLevel(u32),
+    MaxPlayerLevel(u32),
+    Elite,
+    NotElite,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub enum LootDrop {
+    Nothing,
+    Item {
+        item: LootItem,
+        quantity: RangeInclusive<u32>,
+    },
+    Table(String),
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct LootEntry {
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    #[serde(default)]
+    pub conditions: Vec<LootCondition>,
+    pub drop: LootDrop,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct LootTable {
+    #[serde(default)]
+    pub guaranteed: Vec<LootEntry>,
+    #[serde(default = "default_rolls")]
+    pub rolls: u32,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+#[derive(Resource, Deserialize, Debug)]
+pub struct LootTables {
+    pub tables: HashMap<String, LootTable>,
+    pub enemy_tables: HashMap<EnemyKind, String>,
+    pub boss_table: Option<String>,
+    #[serde(default)]
+    pub rift_table: Option<String>,
+    #[serde(default)]
+    pub prop_tables: HashMap<PropKind, String>,
+}
+
+pub struct LootContext {
+    pub player_level: u32,
+    pub elite: bool,
+}
+
+impl LootCondition {
+    fn holds(&self, context: &LootContext) -> bool {
+        match *self {
+            LootCondition::MinPlayerLevel(level) => context.player_level >= level,
+            LootCondition::MaxPlayerLevel(level) => context.player_level <= level,
+            LootCondition::Elite => context.elite,
+            LootCondition::NotElite => !context.elite,
+        }
+    }
+}
+
+impl LootEntry {
+    fn applies(&self, context: &LootContext) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.holds(context))
+    }
+}
+
+impl LootTables {
+    fn load(path: &str) -> Self {
+        let tables: LootTables = load_ron(path).unwrap_or_else(|err| panic!("{err}"));
+        if let Err(err) = tables.validate() {
+            panic!("invalid loot tables in {path}: {err}");
+        }
+        tables
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let referenced = self
+            .enemy_tables
+            .values()
+            .chain(self.boss_table.iter())
+            .chain(self.rift_table.iter())
+            .chain(self.prop_tables.values())
+            .map(|name| (name, "<root>"));
+        let nested = self.tables.iter().flat_map(|(table_name, table)| {
+            table
+                .guaranteed
+                .iter()
+                .chain(table.entries.iter())
+                .filter_map(move |entry| match &entry.drop {
+                    LootDrop::Table(name) => Some((name, table_name.as_str())),
+                    _ => None,
+                })
+        });
+        for (name, referenced_from) in referenced.chain(nested) {
+            if !self.tables.contains_key(name) {
+                return Err(format!(
+                    "table `{name}` referenced from `{referenced_from}` does not exist"
+                ));
+            }
+        }
+        for (name, table) in self.tables.iter() {
+            for entry in table.guaranteed.iter().chain(table.entries.iter()) {
+                if let LootDrop::Item { quantity, .. } = &entry.drop {
+                    if quantity.is_empty() {
+                        return Err(format!("table `{name}` has an empty quantity range"));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn enemy_table(&self, kind: EnemyKind) -> Option<&str> {
+        self.enemy_tables.get(&kind).map(String::as_str)
+    }
+
+    pub fn prop_table(&self, kind: PropKind) -> Option<&str> {
+        self.prop_tables.get(&kind).map(String::as_str)
+    }
+
+    // the single entry point for every drop in the game
+    pub fn roll(
+        &self,
+        table: &str,
+        context: &LootContext,
+        rng: &mut impl Rng,
+    ) -> Vec<(LootItem, u32)> {
+        let mut drops = Vec::new();
+        self.roll_into(table, context, rng, 0, &mut drops);
+        drops
+    }
+
+    fn roll_into(
+        &self,
+        table: &str,
+        context: &LootContext,
+        rng: &mut impl Rng,
+        depth: u32,
+        drops: &mut Vec<(LootItem, u32)>,
+    ) {
+        let Some(loot_table) = self.tables.get(table) else {
+            return;
+        };
+        if depth > MAX_TABLE_DEPTH {
+            warn!("loot table `{table}` nests deeper than {MAX_TABLE_DEPTH} tables, ignoring");
+            return;
+        }
+
+        for entry in loot_table.guaranteed.iter() {
+            if entry.applies(context) {
+                self.resolve(&entry.drop, context, rng, depth, drops);
+            }
+        }
+
+        let candidates: Vec<&LootEntry> = loot_table
+            .entries
+            .iter()
+            .filter(|entry| entry.weight > 0 && entry.applies(context))
+            .collect();
+        for _ in 0..loot_table.rolls {
+            if let Ok(entry) = candidates.choose_weighted(rng, |entry| entry.weight) {
+                self.resolve(&entry.drop, context, rng, depth, drops);
+            }
+        }
+    }
+
+    fn resolve(
+        &self,
+        drop: &LootDrop,
+        context: &LootContext,
+        rng: &mut impl Rng,
+        depth: u32,
+        drops: &mut Vec<(LootItem, u32)>,
+    ) {
+        match drop {
+            LootDrop::Nothing => {}
+            LootDrop::Item { item, quantity } => {
+                let quantity = rng.gen_range(quantity.clone());
+                if quantity > 0 {
+                    drops.push((*item, quantity));
+                }
+            }
+            LootDrop::Table(name) => self.roll_into(name, context, rng, depth + 1, drops),
+        }
+    }
+}
+
+pub fn spawn_drops(
+    commands: &mut Commands,
+    position: Vec2,
+    xp: f32,
+    drops: &[(LootItem, u32)],
+    rng: &mut impl Rng,
+) {
+    for (item, quantity) in drops {
+        for _ in 0..*quantity {
+            let offset =
+                Vec2::from_angle(rng.gen_range(0.0..TAU)) * rng.gen_range(0.0..DROP_SCATTER);
+            let translation = (position + offset).extend(0.);
+            match *item {
+                LootItem::XpGem { multiplier } => {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                custom_size: Some(GEM_SIZE),
+                                color: Color::PINK,
+                                ..default()
+                            },
+                            transform: Transform::from_translation(translation),
+                            ..default()
+                        },
+                        Collider(GEM_SIZE),
+                        XpGem(xp * multiplier),
+                    ));
+                }
+                LootItem::Chest { xp } => {
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                custom_size: Some(CHEST_SIZE),
+                                color: Color::GOLD,
+                                ..default()
+                            },
+                            transform: Transform::from_translation(translation),
+                            ..default()
+                        },
+                        Collider(CHEST_SIZE),
+                        Chest { xp },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    const GEM: LootItem = LootItem::XpGem { multiplier: 1.0 };
+    const CHEST: LootItem = LootItem::Chest { xp: 10.0 };
+
+    fn tables(tables: &str) -> LootTables {
+        ron::from_str(&format!("(tables: {{ {tables} }}, enemy_tables: {{}})")).unwrap()
+    }
+
+    fn context(player_level: u32, elite: bool) -> LootContext {
+        LootContext {
+            player_level,
+            elite,
+        }
+    }
+
+    fn count(drops: &[(LootItem, u32)], item: LootItem) -> u32 {
+        drops
+            .iter()
+            .filter(|(dropped, _)| *dropped == item)
+            .map(|(_, quantity)| quantity)
+            .sum()
+    }
+
+    #[test]
+    fn picks_entries_by_weight() {
+        let tables = tables(
+            r#""t": (rolls: 4000, entries: [
+                (weight: 3, drop: Item(item: XpGem(multiplier: 1.0), quantity: (start: 1, end: 1))),
+                (weight: 1, drop: Item(item: Chest(xp: 10.0), quantity: (start: 1, end: 1))),
+                (weight: 0, drop: Item(item: XpGem(multiplier: 2.0), quantity: (start: 1, end: 1))),
+            ])"#,
+        );
+        let mut rng = StdRng::seed_from_u64(7);
+        let drops = tables.roll("t", &context(1, false), &mut rng);
+
+        let gems = count(&drops, GEM);
+        assert_eq!(gems + count(&drops, CHEST), 4000);
+        assert!((2850..=3150).contains(&gems), "{gems} gems out of 4000");
+        assert_eq!(count(&drops, LootItem::XpGem { multiplier: 2.0 }), 0);
+    }
+
+    #[test]
+    fn same_seed_rolls_the_same_drops() {
+        let tables = tables(
+            r#""t": (rolls: 20, entries: [
+                (weight: 1, drop: Nothing),
+                (weight: 1, drop: Item(item: XpGem(multiplier: 1.0), quantity: (start: 1, end: 5))),
+            ])"#,
+        );
+        let roll = |seed| tables.roll("t", &context(1, false), &mut StdRng::seed_from_u64(seed));
+        assert_eq!(roll(3), roll(3));
+    }
+
+    #[test]
+    fn nested_tables_roll_into_the_same_drops() {
+        let tables = tables(
+            r#""outer": (
+                guaranteed: [(drop: Item(item: Chest(xp: 10.0), quantity: (start: 1, end: 1)))],
+                entries: [(drop: Table("inner"))],
+            ),
+            "inner": (
+                guaranteed: [(drop: Item(item: XpGem(multiplier: 1.0), quantity: (start: 2, end: 2)))],
+            )"#,
+        );
+        let drops = tables.roll("outer", &context(1, false), &mut StdRng::seed_from_u64(1));
+        assert_eq!(drops, vec![(CHEST, 1), (GEM, 2)]);
+    }
+
+    #[test]
+    fn stops_nesting_after_the_depth_cap() {
+        let tables = tables(
+            r#""loop": (
+                guaranteed: [
+                    (drop: Item(item: XpGem(multiplier: 1.0), quantity: (start: 1, end: 1))),
+                    (drop: Table("loop")),
+                ],
+            )"#,
+        );
+        let drops = tables.roll("loop", &context(1, false), &mut StdRng::seed_from_u64(1));
+        // the root table plus eight nested ones
+        assert_eq!(count(&drops, GEM), MAX_TABLE_DEPTH + 1);
+    }
+
+    #[test]
+    fn conditions_filter_entries() {
+        let tables = tables(
+            r#""t": (
+                guaranteed: [
+                    (conditions: [Elite], drop: Item(item: Chest(xp: 10.0), quantity: (start: 1, end: 1))),
+                    (conditions: [NotElite, MinPlayerLevel(5)], drop: Item(item: XpGem(multiplier: 1.0), quantity: (start: 1, end: 1))),
+                    (conditions: [MaxPlayerLevel(2)], drop: Item(item: XpGem(multiplier: 3.0), quantity: (start: 1, end: 1))),
+                ],
+            )"#,
+        );
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut roll =
+            |player_level, elite| tables.roll("t", &context(player_level, elite), &mut rng);
+
+        assert_eq!(
+            roll(1, true),
+            vec![(CHEST, 1), (LootItem::XpGem { multiplier: 3.0 }, 1)]
+        );
+        assert_eq!(roll(4, false), vec![]);
+        assert_eq!(roll(5, false), vec![(GEM, 1)]);
+        assert_eq!(roll(5, true), vec![(CHEST, 1)]);
+    }
+
+    #[test]
+    fn rejects_bad_tables() {
+        let missing = tables(r#""t": (entries: [(drop: Table("nowhere"))])"#);
+        let err = missing.validate().unwrap_err();
+        assert!(err.contains("`nowhere`"), "{err}");
+
+        let empty_range = tables(
+            r#""t": (entries: [(drop: Item(item: XpGem(multiplier: 1.0), quantity: (start: 3, end: 1)))])"#,
+        );
+        assert!(empty_range.validate().is_err());
+        assert!(tables(r#""t": ()"#).validate().is_ok());
+    }
+}
@@ -1,23 +1,63 @@
+mod ai;
+mod arena;
+mod boss;
+mod bounds;
 mod components;
+mod data;
+mod death;
+mod director;
+mod elite;
 mod enemy;
+mod flocking;
+mod flow_field;
+mod hazard;
+mod loot;
+mod objective;
 
 mod map;
+mod minimap;
 mod player;
+mod props;
 mod resources;
 mod rift;
 mod scenes;
+mod seed;
+mod spatial_grid;
+mod stage;
 mod systems;
+mod terrain;
 mod ui;
 mod events;
 
 use crate::resources::XP;
 use bevy::prelude::*;
+use ai::AiPlugin;
+use arena::ArenaPlugin;
+use boss::BossPlugin;
+use bounds::{ArenaBounds, BoundsPlugin};
+use death::DeathPlugin;
+use director::DirectorPlugin;
+use elite::ElitePlugin;
 use components::{MainCamera, MouseWorldCoords};
 use enemy::EnemyPlugin;
+use flocking::FlockingPlugin;
+use flow_field::FlowFieldPlugin;
+use hazard::HazardPlugin;
+use loot::LootPlugin;
+use map::MapPlugin;
+use minimap::MinimapPlugin;
+use objective::ObjectivePlugin;
 use player::{Player, PlayerPlugin};
-use resources::AppState;
+use props::PropsPlugin;
+use resources::{AppState, GameMode};
 use rift::RiftPlugin;
-use systems::{cursor_world_position, handle_knockback, move_all};
+use seed::SeedPlugin;
+use spatial_grid::SpatialGridPlugin;
+use stage::StagePlugin;
+use systems::{
+    bullet_hit_shootable, cursor_world_position, handle_knockback, move_all,
+    obstacles_stop_projectiles,
+};
 use ui::{Score, UIPlugin};
 
 fn main() {
@@ -27,12 +67,29 @@ fn main() {
         .init_resource::<MouseWorldCoords>()
         .init_resource::<Score>()
         .init_resource::<XP>()
+        .init_resource::<GameMode>()
         .add_plugins((
             PlayerPlugin,
             EnemyPlugin,
-            UIPlugin,
-            RiftPlugin,
-            crate::scenes::ScenesPlugin,
+            BossPlugin,
+            ElitePlugin,
+            DeathPlugin,
+            AiPlugin,
+            LootPlugin,
+            DirectorPlugin,
+            SpatialGridPlugin,
+            FlockingPlugin,
+            (
+                MapPlugin,
+                FlowFieldPlugin,
+                PropsPlugin,
+                ArenaPlugin,
+                BoundsPlugin,
+                HazardPlugin,
+            ),
+            (UIPlugin, MinimapPlugin),
+            (RiftPlugin, ObjectivePlugin, StagePlugin),
+            (SeedPlugin, crate::scenes::ScenesPlugin),
         ))
         .add_systems(Startup, setup)
         .add_systems(Update, bevy::window::close_on_esc)
@@ -42,7 +99,13 @@ fn main() {
         )
         .add_systems(
             FixedUpdate,
-            (move_all, handle_knockback).run_if(in_state(AppState::InGame)),
+            (
+                move_all,
+                handle_knockback,
+                bullet_hit_shootable,
+                obstacles_stop_projectiles,
+            )
+                .run_if(in_state(AppState::InGame)),
         )
         .add_systems(
             PostUpdate,
@@ -58,8 +121,18 @@ fn setup(mut commands: Commands) {
 
 fn camer_follow_player(
     q_player: Query<&Transform, With<Player>>,
-    mut q_camera: Query<&mut Transform, (With<MainCamera>, Without<Player>)>,
+    mut q_camera: Query<
+        (&mut Transform, &OrthographicProjection),
+        (With<MainCamera>, Without<Player>),
+    >,
+    bounds: Option<Res<ArenaBounds>>,
 ) {
-    let mut camera_transform = q_camera.single_mut();
-    camera_transform.translation = q_player.single().translation;
+    let (mut camera_transform, projection) = q_camera.single_mut();
+    let player_position = q_player.single().translation;
+    let Some(bounds) = bounds else {
+        camera_transform.translation = player_position;
+        return;
+    };
+    let position = bounds.clamp_view(player_position.truncate(), projection.area.size());
+    camera_transform.translation = position.extend(player_position.z);
 }
@@ -1,66 +1,290 @@
-use bevy::{prelude::*, utils::HashSet};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_ecs_tilemap::prelude::*;
 
-use crate::{components::MainCamera};
+use crate::arena::Arena;
+use crate::components::MainCamera;
+use crate::seed::RunSeed;
+use crate::terrain::{landmark_at, obstacle_at, shade_at, terrain_at, Obstacle};
 
-const TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 32.0, y: 32.0 };
+pub const TILE_SIZE: TilemapTileSize = TilemapTileSize { x: 32.0, y: 32.0 };
 const CHUNK_SIZE: UVec2 = UVec2 { x: 16, y: 16 };
+// relative to the chunk, keeps scenery between the tiles and everything else
+const PROP_Z: f32 = 5.0;
+const CHUNK_Z: f32 = -10.0;
 const RENDER_CHUNK_SIZE: UVec2 = UVec2 {
     x: CHUNK_SIZE.x * 8,
     y: CHUNK_SIZE.y * 8,
 };
 
+// distances from the camera to chunk centers, in pixels
+const LOAD_RADIUS: f32 = 1100.0;
+const UNLOAD_RADIUS: f32 = 1500.0;
+
 #[derive(Component)]
 pub struct MapPlugin;
 
-#[derive(Default, Debug, Resource)]
-struct ChunkManager {
-    pub spawned_chunks: HashSet<IVec2>,
-}
-
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(TilemapRenderSettings {
             render_chunk_size: RENDER_CHUNK_SIZE,
             ..Default::default()
         })
-            .insert_resource(ChunkManager::default())
-            .add_systems(
-                Update,
-                (spawn_chunks_around_camera, despawn_outofrange_chunks),
-            )
-            .add_plugins(TilemapPlugin);
+        .init_resource::<MapConfig>()
+        .insert_resource(ChunkManager::default())
+        .init_resource::<TileOccupancy>()
+        .add_event::<ChunkEvent>()
+        .add_systems(
+            Update,
+            (regenerate_on_new_seed, unload_far_chunks, load_near_chunks)
+                .chain()
+                .run_if(not(resource_exists::<Arena>())),
+        )
+        .add_plugins(TilemapPlugin);
+    }
+}
+
+// chunks load inside load_radius and only unload past unload_radius,
+// so a camera sitting on a chunk border doesn't keep streaming the same chunks
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct MapConfig {
+    pub load_radius: f32,
+    pub unload_radius: f32,
+}
+
+impl Default for MapConfig {
+    fn default() -> Self {
+        Self {
+            load_radius: LOAD_RADIUS,
+            unload_radius: UNLOAD_RADIUS,
+        }
+    }
+}
+
+// sent in the order chunks stream in and out, so anything living on a chunk can follow it
+#[derive(Event, Clone, Copy, Debug)]
+pub enum ChunkEvent {
+    Loaded(IVec2),
+    Unloaded(IVec2),
+}
+
+#[derive(Default, Debug, Resource)]
+pub struct ChunkManager {
+    pub spawned_chunks: HashMap<IVec2, Entity>,
+    // hidden tilemaps waiting to be repainted for another chunk
+    pool: Vec<Entity>,
+    // survives unloading, so a destroyed prop is still gone when its chunk comes back
+    destroyed_props: HashMap<IVec2, HashSet<IVec2>>,
+}
+
+impl ChunkManager {
+    pub fn chunks_to_load(&self, camera_pos: Vec2, config: &MapConfig) -> Vec<IVec2> {
+        let chunk_extent = chunk_world_size().min_element();
+        let reach = (config.load_radius / chunk_extent).ceil() as i32 + 1;
+        let camera_chunk = world_pos_to_chunk(camera_pos);
+        let mut chunks = Vec::new();
+        for y in -reach..=reach {
+            for x in -reach..=reach {
+                let chunk_pos = camera_chunk + IVec2::new(x, y);
+                if !self.spawned_chunks.contains_key(&chunk_pos)
+                    && chunk_center(chunk_pos).distance(camera_pos) <= config.load_radius
+                {
+                    chunks.push(chunk_pos);
+                }
+            }
+        }
+        chunks
+    }
+
+    pub fn chunks_to_unload(&self, camera_pos: Vec2, config: &MapConfig) -> Vec<IVec2> {
+        let unload_radius = config.unload_radius.max(config.load_radius);
+        self.spawned_chunks
+            .keys()
+            .filter(|chunk_pos| chunk_center(**chunk_pos).distance(camera_pos) > unload_radius)
+            .copied()
+            .collect()
+    }
+
+    pub fn mark_prop_destroyed(&mut self, tile: IVec2) {
+        self.destroyed_props
+            .entry(tile_to_chunk(tile))
+            .or_default()
+            .insert(tile);
+    }
+
+    pub fn is_prop_destroyed(&self, tile: IVec2) -> bool {
+        self.destroyed_props
+            .get(&tile_to_chunk(tile))
+            .is_some_and(|tiles| tiles.contains(&tile))
+    }
+
+    // hands a pooled tilemap to the chunk, skipping any that `usable` turns down
+    fn claim_pooled(
+        &mut self,
+        chunk_pos: IVec2,
+        mut usable: impl FnMut(Entity) -> bool,
+    ) -> Option<Entity> {
+        while let Some(entity) = self.pool.pop() {
+            if usable(entity) {
+                self.spawned_chunks.insert(chunk_pos, entity);
+                return Some(entity);
+            }
+        }
+        None
+    }
+
+    fn release(&mut self, chunk_pos: IVec2) -> Option<Entity> {
+        let entity = self.spawned_chunks.remove(&chunk_pos)?;
+        self.pool.push(entity);
+        Some(entity)
+    }
+
+    // for a fresh world, props destroyed in the old one no longer apply
+    fn release_all(&mut self) -> Vec<(IVec2, Entity)> {
+        let chunks: Vec<(IVec2, Entity)> = self.spawned_chunks.drain().collect();
+        self.pool.extend(chunks.iter().map(|(_, entity)| *entity));
+        self.destroyed_props.clear();
+        chunks
+    }
+}
+
+// solid tiles of the loaded chunks, nothing outside them blocks
+#[derive(Resource, Default, Debug)]
+pub struct TileOccupancy {
+    solid: HashMap<IVec2, Obstacle>,
+}
+
+impl TileOccupancy {
+    pub fn is_solid(&self, tile: IVec2) -> bool {
+        self.solid.contains_key(&tile)
+    }
+
+    pub fn is_solid_at(&self, world_pos: Vec2) -> bool {
+        self.is_solid(world_pos_to_tile(world_pos))
+    }
+
+    // whether a box centered on `center` touches any solid tile
+    pub fn overlaps(&self, center: Vec2, size: Vec2) -> bool {
+        // boxes that only touch a tile edge don't count as inside it
+        let half = (size / 2. - 0.01).max(Vec2::ZERO);
+        let min = world_pos_to_tile(center - half);
+        let max = world_pos_to_tile(center + half);
+        (min.y..=max.y).any(|y| (min.x..=max.x).any(|x| self.is_solid(IVec2::new(x, y))))
+    }
+
+    pub fn block(&mut self, tile: IVec2) {
+        self.solid.insert(tile, Obstacle::Wall);
+    }
+
+    fn fill_chunk(&mut self, chunk_pos: IVec2, seed: u32) {
+        for tile in chunk_tiles(chunk_pos) {
+            if let Some(obstacle) = obstacle_at(tile, seed) {
+                self.solid.insert(tile, obstacle);
+            }
+        }
+    }
+
+    fn clear_chunk(&mut self, chunk_pos: IVec2) {
+        for tile in chunk_tiles(chunk_pos) {
+            self.solid.remove(&tile);
+        }
     }
 }
 
 #[derive(Component)]
 struct Tile;
 
-fn spawn_chunk(commands: &mut Commands, asset_server: &AssetServer, chunk_pos: IVec2) {
+// landmark and obstacle sprites, respawned whenever a chunk is repainted
+#[derive(Component)]
+struct ChunkScenery;
+
+fn chunk_world_size() -> Vec2 {
+    CHUNK_SIZE.as_vec2() * Vec2::new(TILE_SIZE.x, TILE_SIZE.y)
+}
+
+fn chunk_origin(chunk_pos: IVec2) -> IVec2 {
+    chunk_pos * IVec2::new(CHUNK_SIZE.x as i32, CHUNK_SIZE.y as i32)
+}
+
+fn chunk_transform(chunk_pos: IVec2) -> Transform {
+    Transform::from_translation(tile_to_world_pos(chunk_origin(chunk_pos)).extend(CHUNK_Z))
+}
+
+// tiles are centered on their grid position, so a chunk starts half a tile before its origin
+pub fn chunk_center(chunk_pos: IVec2) -> Vec2 {
+    let tile_size = Vec2::new(TILE_SIZE.x, TILE_SIZE.y);
+    tile_to_world_pos(chunk_origin(chunk_pos)) - tile_size / 2. + chunk_world_size() / 2.
+}
+
+pub fn tile_to_chunk(tile: IVec2) -> IVec2 {
+    tile.div_euclid(IVec2::new(CHUNK_SIZE.x as i32, CHUNK_SIZE.y as i32))
+}
+
+pub fn world_pos_to_chunk(world_pos: Vec2) -> IVec2 {
+    tile_to_chunk(world_pos_to_tile(world_pos))
+}
+
+fn spawn_scenery(commands: &mut Commands, tilemap_entity: Entity, chunk_pos: IVec2, seed: u32) {
+    let chunk_origin = chunk_origin(chunk_pos);
+    for tile in chunk_tiles(chunk_pos) {
+        let sprite = match (landmark_at(tile, seed), obstacle_at(tile, seed)) {
+            (Some(landmark), _) => (landmark.color(), landmark.size()),
+            (None, Some(obstacle)) => (obstacle.color(), obstacle.size()),
+            (None, None) => continue,
+        };
+        let position = tile_to_world_pos(tile - chunk_origin).extend(PROP_Z);
+        commands.entity(tilemap_entity).with_children(|parent| {
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: sprite.0,
+                        custom_size: Some(sprite.1),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+                ChunkScenery,
+            ));
+        });
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    chunk_pos: IVec2,
+    seed: u32,
+) -> Entity {
     let tilemap_entity = commands.spawn_empty().id();
     let mut tile_storage = TileStorage::empty(CHUNK_SIZE.into());
+    let chunk_origin = chunk_origin(chunk_pos);
     // Spawn the elements of the tilemap.
-    for x in 0..CHUNK_SIZE.x {
-        for y in 0..CHUNK_SIZE.y {
-            let tile_pos = TilePos { x, y };
-            let tile_entity = commands
-                .spawn(TileBundle {
-                    position: tile_pos,
-                    tilemap_id: TilemapId(tilemap_entity),
-                    ..Default::default()
-                })
-                .id();
-            commands.entity(tilemap_entity).add_child(tile_entity);
-            tile_storage.set(&tile_pos, tile_entity);
-        }
+    for tile in chunk_tiles(chunk_pos) {
+        let local = tile - chunk_origin;
+        let tile_pos = TilePos {
+            x: local.x as u32,
+            y: local.y as u32,
+        };
+        // terrain only depends on the world tile, so a respawned chunk looks the same
+        let tile_entity = commands
+            .spawn(TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(tilemap_entity),
+                texture_index: TileTextureIndex(terrain_at(tile, seed).texture_index()),
+                color: TileColor(shade_at(tile, seed)),
+                ..Default::default()
+            })
+            .id();
+        commands.entity(tilemap_entity).add_child(tile_entity);
+        tile_storage.set(&tile_pos, tile_entity);
     }
+    spawn_scenery(commands, tilemap_entity, chunk_pos, seed);
 
-    let transform = Transform::from_translation(Vec3::new(
-        chunk_pos.x as f32 * CHUNK_SIZE.x as f32 * TILE_SIZE.x,
-        chunk_pos.y as f32 * CHUNK_SIZE.y as f32 * TILE_SIZE.y,
-        -10.0,
-    ));
-    let texture_handle: Handle<Image> = asset_server.load("textures/grass_tile.png");
+    let texture_handle: Handle<Image> = asset_server.load("textures/terrain_tiles.png");
     commands.entity(tilemap_entity).insert((
         TilemapBundle {
             grid_size: TILE_SIZE.into(),
@@ -68,55 +292,283 @@ fn spawn_chunk(commands: &mut Commands, asset_server: &AssetServer, chunk_pos: I
             storage: tile_storage,
             texture: TilemapTexture::Single(texture_handle),
             tile_size: TILE_SIZE,
-            transform,
+            transform: chunk_transform(chunk_pos),
             ..default()
         },
-        Tile, )
-    );
+        Tile,
+    ));
+    tilemap_entity
+}
+
+// the tilemaps chunks are drawn with, taken from the pool when there's one to spare
+#[derive(SystemParam)]
+struct ChunkTilemaps<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    q_chunk: Query<
+        'w,
+        's,
+        (
+            &'static TileStorage,
+            &'static mut Transform,
+            &'static mut Visibility,
+            Option<&'static Children>,
+        ),
+        (With<Tile>, Without<MainCamera>),
+    >,
+    q_tile: Query<'w, 's, (&'static mut TileTextureIndex, &'static mut TileColor)>,
+    q_scenery: Query<'w, 's, (), With<ChunkScenery>>,
+}
+
+impl ChunkTilemaps<'_, '_> {
+    fn show(&mut self, chunk_manager: &mut ChunkManager, chunk_pos: IVec2, seed: u32) {
+        let pooled = chunk_manager.claim_pooled(chunk_pos, |entity| {
+            if self.q_chunk.contains(entity) {
+                return true;
+            }
+            // no longer a whole tilemap, nothing else would clean it up
+            if let Some(entity_commands) = self.commands.get_entity(entity) {
+                entity_commands.despawn_recursive();
+            }
+            false
+        });
+        let pooled = pooled.and_then(|entity| {
+            self.q_chunk
+                .get_mut(entity)
+                .ok()
+                .map(|chunk| (entity, chunk))
+        });
+        let Some((entity, (storage, mut transform, mut visibility, children))) = pooled else {
+            let entity = spawn_chunk(&mut self.commands, &self.asset_server, chunk_pos, seed);
+            chunk_manager.spawned_chunks.insert(chunk_pos, entity);
+            return;
+        };
+
+        // repaint the pooled tilemap in place instead of rebuilding its tiles
+        let chunk_origin = chunk_origin(chunk_pos);
+        for tile in chunk_tiles(chunk_pos) {
+            let local = tile - chunk_origin;
+            let tile_pos = TilePos {
+                x: local.x as u32,
+                y: local.y as u32,
+            };
+            if let Some((mut texture_index, mut color)) = storage
+                .get(&tile_pos)
+                .and_then(|tile_entity| self.q_tile.get_mut(tile_entity).ok())
+            {
+                texture_index.0 = terrain_at(tile, seed).texture_index();
+                color.0 = shade_at(tile, seed);
+            }
+        }
+        for child in children.into_iter().flatten() {
+            if self.q_scenery.contains(*child) {
+                self.commands.entity(*child).despawn_recursive();
+            }
+        }
+        spawn_scenery(&mut self.commands, entity, chunk_pos, seed);
+        *transform = chunk_transform(chunk_pos);
+        *visibility = Visibility::Inherited;
+    }
 }
 
-fn spawn_chunks_around_camera(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+fn load_near_chunks(
+    config: Res<MapConfig>,
+    run_seed: Res<RunSeed>,
     q_camera: Query<&Transform, With<MainCamera>>,
     mut chunk_manager: ResMut<ChunkManager>,
+    mut occupancy: ResMut<TileOccupancy>,
+    mut ev_chunk: EventWriter<ChunkEvent>,
+    mut tilemaps: ChunkTilemaps,
 ) {
-    for transform in q_camera.iter() {
-        let camera_chunk_pos = camera_pos_to_chunk_pos(&transform.translation.xy());
-        for y in (camera_chunk_pos.y - 2)..(camera_chunk_pos.y + 2) {
-            for x in (camera_chunk_pos.x - 2)..(camera_chunk_pos.x + 2) {
-                if !chunk_manager.spawned_chunks.contains(&IVec2::new(x, y)) {
-                    chunk_manager.spawned_chunks.insert(IVec2::new(x, y));
-                    spawn_chunk(&mut commands, &asset_server, IVec2::new(x, y));
-                }
+    let Ok(camera_transform) = q_camera.get_single() else {
+        return;
+    };
+    let chunks = chunk_manager.chunks_to_load(camera_transform.translation.xy(), &config);
+    if chunks.is_empty() {
+        return;
+    }
+
+    let seed = run_seed.terrain_seed();
+    for chunk_pos in chunks {
+        occupancy.fill_chunk(chunk_pos, seed);
+        ev_chunk.send(ChunkEvent::Loaded(chunk_pos));
+        tilemaps.show(&mut chunk_manager, chunk_pos, seed);
+    }
+}
+
+fn unload_far_chunks(
+    config: Res<MapConfig>,
+    q_camera: Query<&Transform, With<MainCamera>>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    mut occupancy: ResMut<TileOccupancy>,
+    mut q_visibility: Query<&mut Visibility, With<Tile>>,
+    mut ev_chunk: EventWriter<ChunkEvent>,
+) {
+    let Ok(camera_transform) = q_camera.get_single() else {
+        return;
+    };
+    let chunks = chunk_manager.chunks_to_unload(camera_transform.translation.xy(), &config);
+    for chunk_pos in chunks {
+        occupancy.clear_chunk(chunk_pos);
+        ev_chunk.send(ChunkEvent::Unloaded(chunk_pos));
+        if let Some(entity) = chunk_manager.release(chunk_pos) {
+            if let Ok(mut visibility) = q_visibility.get_mut(entity) {
+                *visibility = Visibility::Hidden;
             }
         }
     }
 }
 
-fn despawn_outofrange_chunks(
-    mut commands: Commands,
-    q_camera: Query<&Transform, With<Camera>>,
-    q_chunks: Query<(Entity, &Transform), With<Tile>>,
+// a new run seed means new terrain, everything loaded is stale
+fn regenerate_on_new_seed(
+    run_seed: Res<RunSeed>,
     mut chunk_manager: ResMut<ChunkManager>,
+    mut occupancy: ResMut<TileOccupancy>,
+    mut q_visibility: Query<&mut Visibility, With<Tile>>,
+    mut ev_chunk: EventWriter<ChunkEvent>,
 ) {
-    for camera_transform in q_camera.iter() {
-        for (entity, chunk_transform) in q_chunks.iter() {
-            let chunk_pos = chunk_transform.translation.xy();
-            let distance = camera_transform.translation.xy().distance(chunk_pos);
-            if distance > 320.0 {
-                let x = (chunk_pos.x / (CHUNK_SIZE.x as f32 * TILE_SIZE.x)).floor() as i32;
-                let y = (chunk_pos.y / (CHUNK_SIZE.y as f32 * TILE_SIZE.y)).floor() as i32;
-                chunk_manager.spawned_chunks.remove(&IVec2::new(x, y));
-                commands.entity(entity).despawn_recursive();
-            }
+    if !run_seed.is_changed() {
+        return;
+    }
+    *occupancy = TileOccupancy::default();
+    for (chunk_pos, entity) in chunk_manager.release_all() {
+        ev_chunk.send(ChunkEvent::Unloaded(chunk_pos));
+        if let Ok(mut visibility) = q_visibility.get_mut(entity) {
+            *visibility = Visibility::Hidden;
         }
     }
 }
 
-fn camera_pos_to_chunk_pos(camera_pos: &Vec2) -> IVec2 {
-    let camera_pos = camera_pos.as_ivec2();
-    let chunk_size: IVec2 = IVec2::new(CHUNK_SIZE.x as i32, CHUNK_SIZE.y as i32);
-    let tile_size: IVec2 = IVec2::new(TILE_SIZE.x as i32, TILE_SIZE.y as i32);
-    camera_pos / (chunk_size * tile_size)
+pub fn world_pos_to_tile(world_pos: Vec2) -> IVec2 {
+    // tiles are centered on their grid position
+    (world_pos / Vec2::new(TILE_SIZE.x, TILE_SIZE.y) + 0.5)
+        .floor()
+        .as_ivec2()
+}
+
+pub fn tile_to_world_pos(tile: IVec2) -> Vec2 {
+    tile.as_vec2() * Vec2::new(TILE_SIZE.x, TILE_SIZE.y)
+}
+
+pub fn chunk_tiles(chunk_pos: IVec2) -> impl Iterator<Item = IVec2> {
+    let chunk_size = IVec2::new(CHUNK_SIZE.x as i32, CHUNK_SIZE.y as i32);
+    let origin = chunk_pos * chunk_size;
+    (0..chunk_size.y).flat_map(move |y| (0..chunk_size.x).map(move |x| origin + IVec2::new(x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_all(manager: &mut ChunkManager, camera_pos: Vec2, config: &MapConfig) {
+        for chunk_pos in manager.chunks_to_load(camera_pos, config) {
+            let entity = Entity::from_raw(manager.spawned_chunks.len() as u32);
+            manager.spawned_chunks.insert(chunk_pos, entity);
+        }
+    }
+
+    #[test]
+    fn loads_every_chunk_inside_the_load_radius() {
+        let manager = ChunkManager::default();
+        let config = MapConfig::default();
+        let camera_pos = Vec2::new(100., -40.);
+        let chunks: HashSet<IVec2> = manager
+            .chunks_to_load(camera_pos, &config)
+            .into_iter()
+            .collect();
+
+        let expected: HashSet<IVec2> = (-6..=6)
+            .flat_map(|y| (-6..=6).map(move |x| IVec2::new(x, y)))
+            .filter(|chunk_pos| chunk_center(*chunk_pos).distance(camera_pos) <= LOAD_RADIUS)
+            .collect();
+        assert!(chunks.contains(&world_pos_to_chunk(camera_pos)));
+        assert_eq!(chunks, expected);
+    }
+
+    #[test]
+    fn loaded_chunks_are_not_loaded_again() {
+        let mut manager = ChunkManager::default();
+        let config = MapConfig::default();
+        load_all(&mut manager, Vec2::ZERO, &config);
+        assert!(manager.chunks_to_load(Vec2::ZERO, &config).is_empty());
+        assert!(manager.chunks_to_unload(Vec2::ZERO, &config).is_empty());
+    }
+
+    #[test]
+    fn chunks_between_the_radii_stay_loaded() {
+        let mut manager = ChunkManager::default();
+        let config = MapConfig::default();
+        load_all(&mut manager, Vec2::ZERO, &config);
+        let chunk_pos = IVec2::new(-2, 0);
+        assert!(manager.spawned_chunks.contains_key(&chunk_pos));
+
+        // out of load range but still inside the unload radius
+        let camera_pos = Vec2::new(400., 0.);
+        let distance = chunk_center(chunk_pos).distance(camera_pos);
+        assert!(distance > LOAD_RADIUS && distance <= UNLOAD_RADIUS);
+        assert!(!manager
+            .chunks_to_unload(camera_pos, &config)
+            .contains(&chunk_pos));
+        assert!(!manager
+            .chunks_to_load(camera_pos, &config)
+            .iter()
+            .any(|chunk| manager.spawned_chunks.contains_key(chunk)));
+    }
+
+    #[test]
+    fn chunks_past_the_unload_radius_unload() {
+        let mut manager = ChunkManager::default();
+        let config = MapConfig::default();
+        load_all(&mut manager, Vec2::ZERO, &config);
+
+        let camera_pos = Vec2::new(800., 0.);
+        let unloaded = manager.chunks_to_unload(camera_pos, &config);
+        assert!(unloaded.contains(&IVec2::new(-2, 0)));
+        for chunk_pos in manager.spawned_chunks.keys() {
+            let far = chunk_center(*chunk_pos).distance(camera_pos) > UNLOAD_RADIUS;
+            assert_eq!(unloaded.contains(chunk_pos), far, "chunk {chunk_pos}");
+        }
+
+        let gone = Vec2::splat(10_000.);
+        assert_eq!(
+            manager.chunks_to_unload(gone, &config).len(),
+            manager.spawned_chunks.len()
+        );
+    }
+
+    #[test]
+    fn released_tilemaps_are_reused() {
+        let mut manager = ChunkManager::default();
+        let entity = Entity::from_raw(7);
+        manager.spawned_chunks.insert(IVec2::new(0, 0), entity);
+        assert_eq!(manager.release(IVec2::new(0, 0)), Some(entity));
+        assert!(manager.spawned_chunks.is_empty());
+
+        assert_eq!(
+            manager.claim_pooled(IVec2::new(5, 5), |_| true),
+            Some(entity)
+        );
+        assert_eq!(manager.spawned_chunks.get(&IVec2::new(5, 5)), Some(&entity));
+        // an empty pool means a fresh tilemap has to be spawned
+        assert_eq!(manager.claim_pooled(IVec2::new(6, 5), |_| true), None);
+    }
+
+    #[test]
+    fn unusable_pooled_tilemaps_are_skipped() {
+        let mut manager = ChunkManager::default();
+        let (broken, whole) = (Entity::from_raw(1), Entity::from_raw(2));
+        manager.pool = vec![whole, broken];
+
+        let mut turned_down = Vec::new();
+        let claimed = manager.claim_pooled(IVec2::ZERO, |entity| {
+            if entity == broken {
+                turned_down.push(entity);
+                return false;
+            }
+            true
+        });
+        assert_eq!(claimed, Some(whole));
+        assert_eq!(turned_down, vec![broken]);
+        assert!(manager.pool.is_empty());
+    }
 }
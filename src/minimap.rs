@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    utils::HashMap,
+};
+
+use crate::boss::Boss;
+use crate::components::{Chest, XpGem};
+use crate::enemy::Enemy;
+use crate::player::Player;
+use crate::resources::AppState::InGame;
+use crate::rift::Rift;
+
+// pixels of the minimap texture, it is drawn at the same size on screen
+const MINIMAP_SIZE: u32 = 160;
+// world units per minimap pixel
+const MINIMAP_SCALE: f32 = 20.0;
+const MINIMAP_UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+// enemies are counted in square cells of this many pixels
+const DENSITY_CELL: i32 = 4;
+// this many enemies in one cell show at full strength
+const DENSITY_FULL: u32 = 6;
+
+const BACKGROUND_COLOR: [u8; 4] = [20, 20, 25, 170];
+const PLAYER_COLOR: [u8; 4] = [154, 205, 50, 255];
+const ENEMY_COLOR: [u8; 3] = [230, 40, 40];
+const RIFT_COLOR: [u8; 4] = [180, 60, 255, 255];
+const BOSS_COLOR: [u8; 4] = [128, 0, 0, 255];
+const GEM_COLOR: [u8; 4] = [240, 230, 90, 255];
+const CHEST_COLOR: [u8; 4] = [255, 170, 0, 255];
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapConfig>()
+            .add_systems(OnEnter(InGame), setup_minimap.run_if(run_once()))
+            .add_systems(Update, draw_minimap.run_if(in_state(InGame)));
+    }
+}
+
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct MinimapConfig {
+    pub size: u32,
+    pub scale: f32,
+    pub update_interval: Duration,
+}
+
+impl Default for MinimapConfig {
+    fn default() -> Self {
+        Self {
+            size: MINIMAP_SIZE,
+            scale: MINIMAP_SCALE,
+            update_interval: MINIMAP_UPDATE_INTERVAL,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Minimap {
+    image: Handle<Image>,
+    refresh: Timer,
+}
+
+// the texture the minimap is drawn into, centered on the player
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    size: i32,
+    center: Vec2,
+    scale: f32,
+}
+
+impl Canvas<'_> {
+    fn pixel_at(&self, world_pos: Vec2) -> IVec2 {
+        let offset = ((world_pos - self.center) / self.scale).round().as_ivec2();
+        // image rows go down, the world goes up
+        IVec2::new(self.size / 2 + offset.x, self.size / 2 - offset.y)
+    }
+
+    fn contains(&self, pixel: IVec2) -> bool {
+        pixel.cmpge(IVec2::ZERO).all() && pixel.cmplt(IVec2::splat(self.size)).all()
+    }
+
+    fn fill(&mut self, color: [u8; 4]) {
+        for pixel in self.data.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    fn dot(&mut self, pixel: IVec2, radius: i32, color: [u8; 4]) {
+        for y in pixel.y - radius..=pixel.y + radius {
+            for x in pixel.x - radius..=pixel.x + radius {
+                if !self.contains(IVec2::new(x, y)) {
+                    continue;
+                }
+                let index = ((y * self.size + x) * 4) as usize;
+                self.data[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    // markers that matter off screen stick to the border, pointing the way
+    fn marker(&mut self, world_pos: Vec2, radius: i32, color: [u8; 4]) {
+        let pixel = self
+            .pixel_at(world_pos)
+            .clamp(IVec2::splat(radius), IVec2::splat(self.size - 1 - radius));
+        self.dot(pixel, radius, color);
+    }
+}
+
+fn setup_minimap(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<MinimapConfig>,
+) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: config.size,
+            height: config.size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &BACKGROUND_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+    commands.spawn((
+        ImageBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(5.0),
+                right: Val::Px(5.0),
+                width: Val::Px(config.size as f32),
+                height: Val::Px(config.size as f32),
+                ..default()
+            },
+            image: UiImage::new(image.clone()),
+            ..default()
+        },
+        Minimap {
+            image,
+            refresh: Timer::new(config.update_interval, TimerMode::Repeating),
+        },
+    ));
+}
+
+// everything on the minimap besides the player
+#[derive(SystemParam)]
+struct MinimapMarkers<'w, 's> {
+    q_enemy: Query<'w, 's, &'static Transform, (With<Enemy>, Without<Boss>)>,
+    q_rift: Query<'w, 's, &'static Transform, With<Rift>>,
+    q_boss: Query<'w, 's, &'static Transform, With<Boss>>,
+    q_gem: Query<'w, 's, &'static Transform, With<XpGem>>,
+    q_chest: Query<'w, 's, &'static Transform, With<Chest>>,
+}
+
+fn draw_minimap(
+    time: Res<Time>,
+    config: Res<MinimapConfig>,
+    mut images: ResMut<Assets<Image>>,
+    mut q_minimap: Query<(&mut Minimap, &mut Style)>,
+    q_player: Query<&Transform, With<Player>>,
+    markers: MinimapMarkers,
+) {
+    let Ok((mut minimap, mut style)) = q_minimap.get_single_mut() else {
+        return;
+    };
+    minimap.refresh.set_duration(config.update_interval);
+    minimap.refresh.tick(time.delta());
+    if !minimap.refresh.just_finished() {
+        return;
+    }
+    let (Ok(player_transform), Some(image)) =
+        (q_player.get_single(), images.get_mut(&minimap.image))
+    else {
+        return;
+    };
+
+    // the texture was sized for the config of the first run, which may have changed since
+    if image.width() != config.size {
+        image.resize(Extent3d {
+            width: config.size,
+            height: config.size,
+            depth_or_array_layers: 1,
+        });
+        style.width = Val::Px(config.size as f32);
+        style.height = Val::Px(config.size as f32);
+    }
+
+    let size = image.width() as i32;
+    let mut canvas = Canvas {
+        data: &mut image.data,
+        size,
+        center: player_transform.translation.truncate(),
+        scale: config.scale,
+    };
+    canvas.fill(BACKGROUND_COLOR);
+
+    for transform in markers.q_gem.iter() {
+        let pixel = canvas.pixel_at(transform.translation.truncate());
+        canvas.dot(pixel, 0, GEM_COLOR);
+    }
+    for transform in markers.q_chest.iter() {
+        let pixel = canvas.pixel_at(transform.translation.truncate());
+        canvas.dot(pixel, 1, CHEST_COLOR);
+    }
+
+    // a dot per crowded cell instead of per enemy, darker the more there are
+    let mut density: HashMap<IVec2, u32> = HashMap::new();
+    for transform in markers.q_enemy.iter() {
+        let pixel = canvas.pixel_at(transform.translation.truncate());
+        if canvas.contains(pixel) {
+            *density
+                .entry(pixel.div_euclid(IVec2::splat(DENSITY_CELL)))
+                .or_default() += 1;
+        }
+    }
+    for (cell, count) in density {
+        let strength = count.min(DENSITY_FULL) as f32 / DENSITY_FULL as f32;
+        let alpha = (110. + 145. * strength) as u8;
+        let [r, g, b] = ENEMY_COLOR;
+        let center = cell * DENSITY_CELL + IVec2::splat(DENSITY_CELL / 2);
+        canvas.dot(center, 1, [r, g, b, alpha]);
+    }
+
+    for transform in markers.q_rift.iter() {
+        canvas.marker(transform.translation.truncate(), 2, RIFT_COLOR);
+    }
+    for transform in markers.q_boss.iter() {
+        canvas.marker(transform.translation.truncate(), 3, BOSS_COLOR);
+    }
+    let center = IVec2::splat(canvas.size / 2);
+    canvas.dot(center, 2, PLAYER_COLOR);
+}
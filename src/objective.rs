@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+
+use crate::bounds::ArenaBounds;
+use crate::director::WaveDirector;
+use crate::player::Player;
+use crate::resources::AppState::{self, InGame};
+use crate::resources::GameMode;
+use crate::rift::{
+    random_point_on_unit_circle, spawn_rift_at, AnchoredRift, RiftClosed, RiftKind,
+    RIFT_BOUNDS_MARGIN,
+};
+use crate::seed::RiftRng;
+
+const RIFTS_TO_CLOSE: u32 = 10;
+const FINAL_RIFT_SPAWN_RADIUS: f32 = 600.0;
+
+pub struct ObjectivePlugin;
+
+impl Plugin for ObjectivePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ObjectiveConfig>()
+            .add_systems(OnEnter(InGame), setup_objective)
+            .add_systems(
+                Update,
+                (count_closed_rifts, open_final_rift, win_when_objective_done)
+                    .chain()
+                    .run_if(in_state(InGame))
+                    .run_if(resource_equals(GameMode::BreakTheRifts)),
+            );
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct ObjectiveConfig {
+    pub rifts_to_close: u32,
+    // once enough rifts are closed, a boss rift has to be closed as well
+    pub final_rift: bool,
+}
+
+impl Default for ObjectiveConfig {
+    fn default() -> Self {
+        Self {
+            rifts_to_close: RIFTS_TO_CLOSE,
+            final_rift: true,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Objective {
+    pub config: ObjectiveConfig,
+    pub rifts_closed: u32,
+    pub final_rift: Option<Entity>,
+    pub final_rift_closed: bool,
+}
+
+impl Objective {
+    pub fn rifts_done(&self) -> bool {
+        self.rifts_closed >= self.config.rifts_to_close
+    }
+
+    pub fn completed(&self) -> bool {
+        self.rifts_done() && (!self.config.final_rift || self.final_rift_closed)
+    }
+}
+
+fn setup_objective(mut commands: Commands, config: Res<ObjectiveConfig>) {
+    commands.insert_resource(Objective {
+        config: config.clone(),
+        rifts_closed: 0,
+        final_rift: None,
+        final_rift_closed: false,
+    });
+}
+
+fn count_closed_rifts(
+    mut objective: ResMut<Objective>,
+    mut ev_rift_closed: EventReader<RiftClosed>,
+) {
+    for ev in ev_rift_closed.read() {
+        if objective.final_rift == Some(ev.entity) {
+            objective.final_rift_closed = true;
+        } else {
+            objective.rifts_closed += 1;
+        }
+    }
+}
+
+fn open_final_rift(
+    mut commands: Commands,
+    mut objective: ResMut<Objective>,
+    director: Res<WaveDirector>,
+    q_player: Query<&Transform, With<Player>>,
+    bounds: Option<Res<ArenaBounds>>,
+    mut rng: ResMut<RiftRng>,
+) {
+    if !objective.config.final_rift || !objective.rifts_done() || objective.final_rift.is_some() {
+        return;
+    }
+    let mut location = q_player.single().translation.truncate()
+        + random_point_on_unit_circle(FINAL_RIFT_SPAWN_RADIUS, &mut rng.0);
+    // outside the walls it couldn't be shot, and the run couldn't be won
+    if let Some(bounds) = bounds.as_deref() {
+        location = bounds.keep_inside(location, RIFT_BOUNDS_MARGIN, &mut rng.0);
+    }
+    let entity = spawn_rift_at(
+        &mut commands,
+        RiftKind::Boss,
+        location,
+        &director.difficulty(),
+    );
+    commands.entity(entity).insert(AnchoredRift);
+    objective.final_rift = Some(entity);
+}
+
+fn win_when_objective_done(objective: Res<Objective>, mut state: ResMut<NextState<AppState>>) {
+    if objective.completed() {
+        state.set(AppState::Victory);
+    }
+}
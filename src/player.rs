@@ -2,13 +2,13 @@ use std::time::Duration;
 
 use bevy::{math::vec3, prelude::*, sprite::collide_aabb::collide};
 
-use crate::components::XpGem;
-use crate::events::{PlayerDies, PlayerHealthChanged};
+use crate::components::{Chest, XpGem};
+use crate::events::{DamagePlayer, PlayerDies, PlayerHealthChanged};
 use crate::resources::AppState::InGame;
 use crate::resources::XP;
 use crate::{
-    components::{Collider, Health, MouseWorldCoords, Movable, Velocity},
-    enemy::Enemy,
+    components::{BlockedByObstacles, Collider, Health, MouseWorldCoords, Movable, Velocity},
+    enemy::{ContactDamage, Enemy, EnemyProjectile},
 };
 
 const PLAYER_SIZE: Vec2 = Vec2::new(50.0, 50.0);
@@ -29,6 +29,7 @@ const BULLET_SPEED: f32 = 500.0;
 const BULLET_SIZE: Vec2 = Vec2::new(5.0, 5.0);
 
 const PICKUP_RADIUS: f32 = 75.0;
+const PLAYER_INVULNERABILITY_WINDOW: Duration = Duration::from_millis(500);
 
 #[derive(Component)]
 pub struct Player;
@@ -63,14 +64,28 @@ struct Invulnerable {
     timer: Timer,
 }
 
+#[derive(Component)]
+pub struct InvulnerabilityWindow(pub Duration);
+
 pub struct PlayerPlugin;
 
+// everything that sends DamagePlayer runs in FixedUpdate before this
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlayerDamageSet;
+
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(InGame), setup_player)
+        app.add_event::<DamagePlayer>()
+            .add_systems(OnEnter(InGame), setup_player)
             .add_systems(
                 FixedUpdate,
-                (rotate_around_player, enemy_hits_player).run_if(in_state(InGame)),
+                (
+                    rotate_around_player,
+                    (tick_contact_cooldowns, resolve_player_damage)
+                        .chain()
+                        .in_set(PlayerDamageSet),
+                )
+                    .run_if(in_state(InGame)),
             )
             .add_systems(
                 Update,
@@ -80,6 +95,7 @@ impl Plugin for PlayerPlugin {
                     despawn_bullets,
                     countdown_invulnerability,
                     pickup_xp_gem,
+                    pickup_chest,
                     tick_weapon_cooldown,
                 )
                     .run_if(in_state(InGame)),
@@ -110,6 +126,8 @@ fn setup_player(mut commands: Commands, mut ev_player_health: EventWriter<Player
             Movable {
                 move_speed: PLAYER_SPEED,
             },
+            InvulnerabilityWindow(PLAYER_INVULNERABILITY_WINDOW),
+            BlockedByObstacles,
         ))
         .id();
 
@@ -158,8 +176,11 @@ fn setup_player(mut commands: Commands, mut ev_player_health: EventWriter<Player
     commands.entity(main_weapon).push_children(&[nozzle]);
 }
 
-fn player_input(input: Res<Input<KeyCode>>, mut query: Query<&mut Velocity, With<Player>>) {
-    let mut velocity = query.single_mut();
+fn player_input(
+    input: Res<Input<KeyCode>>,
+    mut query: Query<(&mut Velocity, &Movable), With<Player>>,
+) {
+    let (mut velocity, movable) = query.single_mut();
 
     let mut vector = Vec2::ZERO;
     if input.pressed(KeyCode::A) {
@@ -179,7 +200,7 @@ fn player_input(input: Res<Input<KeyCode>>, mut query: Query<&mut Velocity, With
         velocity.x = 0.;
         velocity.y = 0.;
     } else {
-        let displacement = vector.normalize() * PLAYER_SPEED;
+        let displacement = vector.normalize() * movable.move_speed;
         velocity.x = displacement.x;
         velocity.y = displacement.y;
     }
@@ -282,40 +303,83 @@ fn countdown_invulnerability(
     }
 }
 
-fn enemy_hits_player(
+fn tick_contact_cooldowns(mut q_enemy: Query<&mut ContactDamage>, time: Res<Time>) {
+    for mut contact_damage in q_enemy.iter_mut() {
+        contact_damage.cooldown.tick(time.delta());
+    }
+}
+
+fn resolve_player_damage(
     mut commands: Commands,
     mut q_player: Query<
-        (&Transform, &Collider, &mut Health, Entity),
+        (
+            &Transform,
+            &Collider,
+            &mut Health,
+            &InvulnerabilityWindow,
+            Entity,
+        ),
         (With<Player>, Without<Invulnerable>),
     >,
-    q_enemy: Query<(&Transform, &Collider), With<Enemy>>,
+    mut q_enemy: Query<(&Transform, &Collider, &mut ContactDamage), With<Enemy>>,
+    q_projectile: Query<(&Transform, &Collider, &EnemyProjectile, Entity)>,
+    mut ev_damage_player: EventReader<DamagePlayer>,
     mut ev_player_health: EventWriter<PlayerHealthChanged>,
     mut ev_player_dies: EventWriter<PlayerDies>,
 ) {
-    for (player_transform, player_collider, mut player_health, entity) in q_player.iter_mut() {
-        for (enemy_transform, enemy_collider) in q_enemy.iter() {
-            if let Some(_) = collide(
-                player_transform.translation,
-                player_collider.0,
-                enemy_transform.translation,
-                enemy_collider.0,
-            ) {
-                player_health.current -= 1.;
-                ev_player_health.send(PlayerHealthChanged {
-                    current: player_health.current,
-                    max: player_health.max,
-                });
-                commands.entity(entity).insert(Invulnerable {
-                    timer: Timer::new(Duration::from_secs(1), TimerMode::Once),
-                });
-                if player_health.current <= 0. {
-                    ev_player_dies.send(PlayerDies);
-                }
-
-                return;
-            }
+    let Ok((player_transform, player_collider, mut player_health, window, entity)) =
+        q_player.get_single_mut()
+    else {
+        ev_damage_player.clear();
+        return;
+    };
+
+    let mut damage: f32 = ev_damage_player.read().map(|ev| ev.amount).sum();
+
+    for (enemy_transform, enemy_collider, mut contact_damage) in q_enemy.iter_mut() {
+        if !contact_damage.cooldown.finished() {
+            continue;
+        }
+        if let Some(_) = collide(
+            player_transform.translation,
+            player_collider.0,
+            enemy_transform.translation,
+            enemy_collider.0,
+        ) {
+            damage += contact_damage.damage;
+            contact_damage.cooldown.reset();
+        }
+    }
+
+    for (projectile_transform, projectile_collider, projectile, projectile_entity) in
+        q_projectile.iter()
+    {
+        if let Some(_) = collide(
+            player_transform.translation,
+            player_collider.0,
+            projectile_transform.translation,
+            projectile_collider.0,
+        ) {
+            commands.entity(projectile_entity).despawn();
+            damage += projectile.damage;
         }
     }
+
+    if damage <= 0. {
+        return;
+    }
+
+    player_health.current -= damage;
+    ev_player_health.send(PlayerHealthChanged {
+        current: player_health.current,
+        max: player_health.max,
+    });
+    commands.entity(entity).insert(Invulnerable {
+        timer: Timer::new(window.0, TimerMode::Once),
+    });
+    if player_health.current <= 0. {
+        ev_player_dies.send(PlayerDies);
+    }
 }
 
 fn tick_weapon_cooldown(mut q_weapon: Query<&mut Weapon>,
@@ -339,3 +403,25 @@ fn pickup_xp_gem(
         }
     }
 }
+
+fn pickup_chest(
+    mut commands: Commands,
+    q_player: Query<(&Transform, &Collider), With<Player>>,
+    q_chest: Query<(&Transform, &Collider, Entity, &Chest)>,
+    mut xp: ResMut<XP>,
+) {
+    let (player_transform, player_collider) = q_player.single();
+    for (transform, collider, entity, chest) in q_chest.iter() {
+        if collide(
+            player_transform.translation,
+            player_collider.0,
+            transform.translation,
+            collider.0,
+        )
+        .is_some()
+        {
+            commands.entity(entity).despawn();
+            xp.0 += chest.xp;
+        }
+    }
+}
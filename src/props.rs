@@ -0,0 +1,113 @@
+use bevy::prelude::*;
+
+use crate::components::{Collider, Health, Shootable};
+use crate::loot::{spawn_drops, LootContext, LootTables};
+use crate::map::{chunk_tiles, tile_to_world_pos, ChunkEvent, ChunkManager};
+use crate::resources::XP;
+use crate::seed::{DropRng, RunSeed};
+use crate::terrain::{prop_at, PropKind};
+
+pub struct PropsPlugin;
+
+impl Plugin for PropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (follow_chunks, break_props).chain());
+    }
+}
+
+#[derive(Component)]
+pub struct DestructibleProp {
+    pub kind: PropKind,
+    pub tile: IVec2,
+    chunk: IVec2,
+}
+
+fn follow_chunks(
+    mut commands: Commands,
+    mut ev_chunk: EventReader<ChunkEvent>,
+    chunk_manager: Res<ChunkManager>,
+    run_seed: Res<RunSeed>,
+    q_prop: Query<(Entity, &DestructibleProp)>,
+) {
+    let seed = run_seed.terrain_seed();
+    for ev in ev_chunk.read() {
+        match *ev {
+            ChunkEvent::Unloaded(chunk_pos) => {
+                for (entity, prop) in q_prop.iter() {
+                    if prop.chunk == chunk_pos {
+                        commands.entity(entity).despawn();
+                    }
+                }
+            }
+            ChunkEvent::Loaded(chunk_pos) => {
+                for tile in chunk_tiles(chunk_pos) {
+                    let Some(kind) = prop_at(tile, seed) else {
+                        continue;
+                    };
+                    if chunk_manager.is_prop_destroyed(tile) {
+                        continue;
+                    }
+                    let stats = kind.stats();
+                    commands.spawn((
+                        SpriteBundle {
+                            sprite: Sprite {
+                                color: stats.color,
+                                custom_size: Some(stats.size),
+                                ..default()
+                            },
+                            transform: Transform::from_translation(
+                                tile_to_world_pos(tile).extend(0.),
+                            ),
+                            ..default()
+                        },
+                        Collider(stats.size),
+                        Health {
+                            current: stats.health,
+                            max: stats.health,
+                        },
+                        Shootable,
+                        DestructibleProp {
+                            kind,
+                            tile,
+                            chunk: chunk_pos,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn break_props(
+    mut commands: Commands,
+    q_prop: Query<(Entity, &DestructibleProp, &Health)>,
+    mut chunk_manager: ResMut<ChunkManager>,
+    loot_tables: Res<LootTables>,
+    xp: Res<XP>,
+    mut rng: Option<ResMut<DropRng>>,
+) {
+    for (entity, prop, health) in q_prop.iter() {
+        if health.current > 0. {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        chunk_manager.mark_prop_destroyed(prop.tile);
+
+        let (Some(table), Some(rng)) = (loot_tables.prop_table(prop.kind), rng.as_mut()) else {
+            continue;
+        };
+        let context = LootContext {
+            player_level: xp.level(),
+            elite: false,
+        };
+        let drops = loot_tables.roll(table, &context, &mut rng.0);
+        let position = tile_to_world_pos(prop.tile);
+        spawn_drops(
+            &mut commands,
+            position,
+            prop.kind.stats().xp,
+            &drops,
+            &mut rng.0,
+        );
+    }
+}
@@ -9,14 +9,29 @@ pub struct Score(pub i32);
 #[derive(Resource, Default)]
 pub struct XP(pub f32);
 
+impl XP {
+    pub fn level(&self) -> u32 {
+        (self.0 / 5.).sqrt() as u32 + 1
+    }
+}
+
 #[derive(States, Default, Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum AppState {
     #[default]
     MainMenu,
     GameOver,
+    Victory,
     InGame,
 }
 
+// picked on the main menu, decides how a run can end
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    #[default]
+    Survival,
+    BreakTheRifts,
+}
+
 #[derive(States, Default, Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum InGameState {
     #[default]
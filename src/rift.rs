@@ -1,33 +1,161 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use rand::prelude::*;
 use rand_distr::{Distribution, UnitCircle};
+use serde::Deserialize;
 use std::time::Duration;
 
+use crate::arena::Arena;
+use crate::boss::spawn_boss;
+use crate::bounds::ArenaBounds;
+use crate::components::{Collider, Health, Shootable};
+use crate::director::{Difficulty, WaveDirector};
+use crate::elite::ForceElite;
+use crate::loot::{spawn_drops, LootContext, LootTables};
 use crate::resources::AppState::InGame;
-use crate::{enemy::prepare_enemy, player::Player};
+use crate::resources::XP;
+use crate::seed::{DropRng, RiftRng, SpawnRng};
+use crate::stage::Stage;
+use crate::ui::Score;
+use crate::{
+    enemy::{prepare_enemy, Enemy, EnemyKind},
+    player::Player,
+};
 
-const RIFT_COLOR: Color = Color::PURPLE;
-const RIFT_SIZE: Vec2 = Vec2::new(100.0, 100.0);
 const RIFT_SPAWN_RADIUS: f32 = 500.0;
+// keeps rifts in bounded arenas clear of the walls
+pub const RIFT_BOUNDS_MARGIN: f32 = 60.0;
+const RIFT_OPENING_DURATION: Duration = Duration::from_millis(1500);
+const RIFT_COLLAPSE_DURATION: Duration = Duration::from_millis(600);
+const RIFT_OPENING_START_SCALE: f32 = 0.1;
+// how far from the rift center enemies can emerge
+const RIFT_ENEMY_SPAWN_OFFSET: f32 = 40.0;
+const ELITE_THREAT_MULTIPLIER: f32 = 3.0;
+const RIFT_CLOSE_SCORE: i32 = 10;
+// xp per gem in the reward for closing a rift
+const RIFT_REWARD_XP: f32 = 2.0;
 
 #[derive(Component)]
 pub struct RiftPlugin;
 
 impl Plugin for RiftPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(InGame), setup_rift_spawning)
+        app.add_event::<RiftClosed>()
+            .add_systems(OnEnter(InGame), setup_rift_spawning)
             .add_systems(
                 Update,
-                (spawn_rift, spawn_enemies, destroy_rift).run_if(in_state(InGame)),
+                (spawn_rift, advance_rift_phases, spawn_enemies, close_rift)
+                    .chain()
+                    .run_if(in_state(InGame)),
             );
     }
 }
 
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RiftKind {
+    Standard,
+    Swarm,
+    Elite,
+    Ranged,
+    Boss,
+}
+
+// where a rift gets the enemies it spawns from
+enum RiftEnemies {
+    Director,
+    Fixed(&'static [(EnemyKind, u32)]),
+    Boss,
+}
+
+struct RiftStats {
+    size: Vec2,
+    color: Color,
+    health: f32,
+    spawn_interval: Duration,
+    lifetime: Duration,
+    enemies: RiftEnemies,
+    elite: bool,
+}
+
+impl RiftKind {
+    fn stats(self) -> RiftStats {
+        match self {
+            RiftKind::Standard => RiftStats {
+                size: Vec2::new(100.0, 100.0),
+                color: Color::PURPLE,
+                health: 30.,
+                spawn_interval: Duration::from_secs(2),
+                lifetime: Duration::from_secs(30),
+                enemies: RiftEnemies::Director,
+                elite: false,
+            },
+            RiftKind::Swarm => RiftStats {
+                size: Vec2::new(140.0, 70.0),
+                color: Color::FUCHSIA,
+                health: 20.,
+                spawn_interval: Duration::from_millis(400),
+                lifetime: Duration::from_secs(20),
+                enemies: RiftEnemies::Fixed(&[(EnemyKind::Grunt, 4), (EnemyKind::Splitter, 1)]),
+                elite: false,
+            },
+            RiftKind::Elite => RiftStats {
+                size: Vec2::new(90.0, 90.0),
+                color: Color::GOLD,
+                health: 40.,
+                spawn_interval: Duration::from_secs(4),
+                lifetime: Duration::from_secs(30),
+                enemies: RiftEnemies::Director,
+                elite: true,
+            },
+            RiftKind::Ranged => RiftStats {
+                size: Vec2::new(70.0, 130.0),
+                color: Color::TEAL,
+                health: 30.,
+                spawn_interval: Duration::from_millis(2500),
+                lifetime: Duration::from_secs(30),
+                enemies: RiftEnemies::Fixed(&[(EnemyKind::Kiter, 3), (EnemyKind::Grunt, 1)]),
+                elite: false,
+            },
+            RiftKind::Boss => RiftStats {
+                size: Vec2::new(180.0, 180.0),
+                color: Color::MAROON,
+                health: 80.,
+                spawn_interval: Duration::from_secs(5),
+                lifetime: Duration::from_secs(60),
+                enemies: RiftEnemies::Boss,
+                elite: false,
+            },
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct RiftClosed {
+    pub entity: Entity,
+}
+
+// opening rifts grow and flash as a warning, only active rifts spawn enemies
+enum RiftPhase {
+    Opening(Timer),
+    Active,
+    Collapsing(Timer),
+}
+
 #[derive(Component)]
-struct Rift {
-    enemies_to_spawn: u32,
+pub struct Rift {
+    kind: RiftKind,
+    phase: RiftPhase,
+    lifetime: Timer,
+    // picked before it can be paid for, so expensive kinds get saved up for
+    next_kind: Option<EnemyKind>,
+    // boss rifts are spent once their boss is out
+    exhausted: bool,
 }
 
+// anchored rifts never collapse on their own, they stay until shot closed
+#[derive(Component)]
+pub struct AnchoredRift;
+
 #[derive(Resource)]
 struct RiftSpawnConfig {
     timer: Timer,
@@ -44,62 +172,261 @@ fn setup_rift_spawning(mut commands: Commands) {
     })
 }
 
-fn random_point_on_unit_circle(radius: f32) -> Vec2 {
-    let mut rng = thread_rng();
-    let point = UnitCircle.sample(&mut rng);
+pub fn random_point_on_unit_circle(radius: f32, rng: &mut impl Rng) -> Vec2 {
+    let point = UnitCircle.sample(rng);
     Vec2::new(point[0], point[1]) * radius
 }
 
+// picks spots around the player for rifts and bosses, inside the arena walls if there are any
+#[derive(SystemParam)]
+pub struct RiftPlacement<'w, 's> {
+    arena: Option<Res<'w, Arena>>,
+    bounds: Option<Res<'w, ArenaBounds>>,
+    pub rng: ResMut<'w, RiftRng>,
+    q_player: Query<'w, 's, &'static Transform, With<Player>>,
+}
+
+impl RiftPlacement<'_, '_> {
+    // arenas place their rifts by hand
+    pub fn random_rift(&mut self) -> Vec2 {
+        let arena_spawn_point = self
+            .arena
+            .as_deref()
+            .and_then(|arena| arena.rift_spawns.choose(&mut self.rng.0))
+            .copied();
+        match arena_spawn_point {
+            Some(location) => self.keep_inside(location, RIFT_BOUNDS_MARGIN),
+            None => self.around_player(RIFT_SPAWN_RADIUS, RIFT_BOUNDS_MARGIN),
+        }
+    }
+
+    pub fn player_position(&self) -> Vec2 {
+        self.q_player.single().translation.truncate()
+    }
+
+    pub fn around_player(&mut self, distance: f32, margin: f32) -> Vec2 {
+        let location =
+            self.player_position() + random_point_on_unit_circle(distance, &mut self.rng.0);
+        self.keep_inside(location, margin)
+    }
+
+    fn keep_inside(&mut self, location: Vec2, margin: f32) -> Vec2 {
+        match self.bounds.as_deref() {
+            Some(bounds) => bounds.keep_inside(location, margin, &mut self.rng.0),
+            None => location,
+        }
+    }
+}
+
 fn spawn_rift(
     mut commands: Commands,
     time: Res<Time>,
     mut config: ResMut<RiftSpawnConfig>,
-    q_player_transform: Query<&Transform, With<Player>>,
+    director: Res<WaveDirector>,
+    stage: Res<Stage>,
+    mut placement: RiftPlacement,
 ) {
+    if !stage.random_rifts {
+        return;
+    }
+    let difficulty = director.difficulty();
+    config.timer.set_duration(difficulty.rift_interval);
     config.timer.tick(time.delta());
-    let player_position = q_player_transform.single().translation.truncate();
 
     if config.timer.finished() {
-        let random_spawn_point = player_position + random_point_on_unit_circle(RIFT_SPAWN_RADIUS);
+        let random_spawn_point = placement.random_rift();
+        let kind = director.pick_rift_kind(&mut placement.rng.0);
+        spawn_rift_at(&mut commands, kind, random_spawn_point, &difficulty);
+    }
+}
 
-        commands.spawn((
+pub fn spawn_rift_at(
+    commands: &mut Commands,
+    kind: RiftKind,
+    location: Vec2,
+    difficulty: &Difficulty,
+) -> Entity {
+    let stats = kind.stats();
+    commands
+        .spawn((
             SpriteBundle {
                 sprite: Sprite {
-                    color: RIFT_COLOR,
-                    custom_size: Some(RIFT_SIZE),
+                    color: stats.color,
+                    custom_size: Some(stats.size),
                     ..default()
                 },
-                transform: Transform::from_xyz(random_spawn_point.x, random_spawn_point.y, 0.0),
+                transform: Transform::from_xyz(location.x, location.y, 0.0)
+                    .with_scale(Vec3::splat(RIFT_OPENING_START_SCALE)),
                 ..default()
             },
             Rift {
-                enemies_to_spawn: 5,
+                kind,
+                phase: RiftPhase::Opening(Timer::new(RIFT_OPENING_DURATION, TimerMode::Once)),
+                lifetime: Timer::new(stats.lifetime, TimerMode::Once),
+                next_kind: None,
+                exhausted: false,
             },
+            Collider(stats.size * RIFT_OPENING_START_SCALE),
+            Health {
+                current: stats.health * difficulty.health_multiplier,
+                max: stats.health * difficulty.health_multiplier,
+            },
+            Shootable,
             EnemySpawnConfig {
-                timer: Timer::new(Duration::from_secs(2), TimerMode::Repeating),
+                timer: Timer::new(stats.spawn_interval, TimerMode::Repeating),
             },
-        ));
-    }
+        ))
+        .id()
 }
 
 fn spawn_enemies(
     mut commands: Commands,
     time: Res<Time>,
     mut q_rift: Query<(&mut EnemySpawnConfig, &mut Rift, &Transform)>,
+    mut director: ResMut<WaveDirector>,
+    q_enemy: Query<(), With<Enemy>>,
+    mut rng: ResMut<SpawnRng>,
 ) {
+    let rng = &mut rng.0;
+    let mut live_enemies = q_enemy.iter().count();
     for (mut config, mut rift, transform) in q_rift.iter_mut() {
+        if !matches!(rift.phase, RiftPhase::Active) {
+            continue;
+        }
         config.timer.tick(time.delta());
-        if config.timer.finished() {
-            commands.spawn(prepare_enemy(&transform.translation.truncate()));
-            rift.enemies_to_spawn -= 1;
+        if !config.timer.finished() || rift.exhausted {
+            continue;
+        }
+
+        let radius = rng.gen_range(0.0..RIFT_ENEMY_SPAWN_OFFSET);
+        let offset = random_point_on_unit_circle(radius, rng);
+        let location = transform.translation.truncate() + offset;
+        let stats = rift.kind.stats();
+        let kind = match stats.enemies {
+            RiftEnemies::Director => director.pick_enemy_kind(rng),
+            RiftEnemies::Fixed(weights) => weights
+                .choose_weighted(rng, |(_, weight)| *weight)
+                .map_or(EnemyKind::Grunt, |(kind, _)| *kind),
+            // bosses are milestones, they don't come out of the threat budget
+            RiftEnemies::Boss => {
+                spawn_boss(&mut commands, location);
+                rift.exhausted = true;
+                continue;
+            }
+        };
+        if director.at_enemy_cap(live_enemies) {
+            continue;
+        }
+        let kind = *rift.next_kind.get_or_insert(kind);
+        let cost = if stats.elite {
+            kind.threat() * ELITE_THREAT_MULTIPLIER
+        } else {
+            kind.threat()
+        };
+        if !director.try_spend(cost) {
+            continue;
+        }
+
+        rift.next_kind = None;
+        live_enemies += 1;
+        let mut enemy = commands.spawn(prepare_enemy(kind, &location));
+        if stats.elite {
+            enemy.insert(ForceElite);
         }
     }
 }
 
-fn destroy_rift(mut commands: Commands, q_rift: Query<(Entity, &Rift)>) {
-    for (entity, rift) in q_rift.iter() {
-        if rift.enemies_to_spawn <= 0 {
-            commands.entity(entity).despawn();
+// shooting a rift down stops its spawns early and pays out a reward
+fn close_rift(
+    mut commands: Commands,
+    q_rift: Query<(Entity, &Transform, &Health), With<Rift>>,
+    loot_tables: Res<LootTables>,
+    xp: Res<XP>,
+    mut score: ResMut<Score>,
+    mut ev_rift_closed: EventWriter<RiftClosed>,
+    mut rng: ResMut<DropRng>,
+) {
+    for (entity, transform, health) in q_rift.iter() {
+        if health.current > 0. {
+            continue;
+        }
+        commands.entity(entity).despawn();
+        score.0 += RIFT_CLOSE_SCORE;
+        ev_rift_closed.send(RiftClosed { entity });
+        let Some(table) = loot_tables.rift_table.as_deref() else {
+            continue;
+        };
+        let context = LootContext {
+            player_level: xp.level(),
+            elite: false,
+        };
+        let drops = loot_tables.roll(table, &context, &mut rng.0);
+        spawn_drops(
+            &mut commands,
+            transform.translation.truncate(),
+            RIFT_REWARD_XP,
+            &drops,
+            &mut rng.0,
+        );
+    }
+}
+
+fn advance_rift_phases(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut q_rift: Query<(
+        Entity,
+        &mut Rift,
+        &mut Transform,
+        &mut Collider,
+        &mut Sprite,
+        Has<AnchoredRift>,
+    )>,
+) {
+    for (entity, mut rift, mut transform, mut collider, mut sprite, anchored) in q_rift.iter_mut() {
+        let stats = rift.kind.stats();
+        let (scale, alpha) = match &mut rift.phase {
+            RiftPhase::Opening(timer) => {
+                timer.tick(time.delta());
+                let t = timer.percent();
+                // flash faster and faster until the rift tears open
+                let flash = (t * t * 40.).sin() * 0.5 + 0.5;
+                (
+                    RIFT_OPENING_START_SCALE + (1. - RIFT_OPENING_START_SCALE) * t,
+                    0.3 + 0.7 * flash,
+                )
+            }
+            RiftPhase::Active => (1., 1.),
+            RiftPhase::Collapsing(timer) => {
+                timer.tick(time.delta());
+                (timer.percent_left(), timer.percent_left())
+            }
+        };
+        transform.scale = Vec3::splat(scale);
+        collider.0 = stats.size * scale;
+        sprite.color = stats.color.with_a(alpha);
+
+        let next = match &rift.phase {
+            RiftPhase::Opening(timer) if timer.finished() => Some(RiftPhase::Active),
+            RiftPhase::Active => {
+                rift.lifetime.tick(time.delta());
+                let spent = rift.exhausted || rift.lifetime.finished();
+                (spent && !anchored).then(|| {
+                    RiftPhase::Collapsing(Timer::new(RIFT_COLLAPSE_DURATION, TimerMode::Once))
+                })
+            }
+            RiftPhase::Collapsing(timer) if timer.finished() => {
+                commands.entity(entity).despawn();
+                None
+            }
+            _ => None,
+        };
+        if let Some(phase) = next {
+            // a collapsing rift can't be shot closed anymore
+            if matches!(phase, RiftPhase::Collapsing(_)) {
+                commands.entity(entity).remove::<Shootable>();
+            }
+            rift.phase = phase;
         }
     }
 }
@@ -1,5 +1,6 @@
 use crate::events::PlayerDies;
 use crate::resources::AppState;
+use crate::seed::RunSeed;
 use bevy::prelude::*;
 
 pub fn game_over_when_player_dies(
@@ -15,16 +16,26 @@ pub fn game_over_when_player_dies(
 #[derive(Component)]
 pub struct GameOver;
 
-pub fn setup_game_over_ui(mut commands: Commands) {
+pub fn setup_game_over_ui(mut commands: Commands, run_seed: Res<RunSeed>) {
     commands.spawn((
-        TextBundle::from_sections([TextSection::new(
-            "Game Over | Press <space> to restart",
-            TextStyle {
-                font_size: 40.,
-                color: Color::BLACK,
-                ..default()
-            },
-        )])
+        TextBundle::from_sections([
+            TextSection::new(
+                "Game Over | Press <space> to restart\n",
+                TextStyle {
+                    font_size: 40.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ),
+            TextSection::new(
+                format!("Seed: {}", run_seed.0),
+                TextStyle {
+                    font_size: 30.,
+                    color: Color::DARK_GREEN,
+                    ..default()
+                },
+            ),
+        ])
             .with_style(Style {
                 position_type: PositionType::Absolute,
                 top: Val::Px(5.0),
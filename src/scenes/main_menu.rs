@@ -1,19 +1,42 @@
-use crate::resources::AppState;
+use crate::resources::{AppState, GameMode};
+use crate::seed::RunSeed;
 use bevy::prelude::*;
 
+// long enough for any u64 seed someone would share
+const MAX_SEED_DIGITS: usize = 18;
+
 #[derive(Component)]
 pub struct MainMenu;
 
+// digits typed on the main menu, an empty input rolls a fresh seed
+#[derive(Resource, Default)]
+pub struct SeedInput(String);
+
 pub fn setup_main_menu(mut commands: Commands) {
     commands.spawn((
-        TextBundle::from_sections([TextSection::new(
-            "Main Menu | Press <space> to start",
-            TextStyle {
-                font_size: 40.,
-                color: Color::BLACK,
+        TextBundle::from_sections([
+            TextSection::new(
+                "Main Menu | Press <space> for survival, <R> to break the rifts\n",
+                TextStyle {
+                    font_size: 40.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ),
+            TextSection::new(
+                "Type a seed, or leave it empty for a random one: ",
+                TextStyle {
+                    font_size: 30.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: 30.,
+                color: Color::DARK_GREEN,
                 ..default()
-            },
-        )])
+            }),
+        ])
         .with_style(Style {
             position_type: PositionType::Absolute,
             top: Val::Px(5.0),
@@ -24,13 +47,42 @@ pub fn setup_main_menu(mut commands: Commands) {
     ));
 }
 
+pub fn enter_seed(
+    mut input: ResMut<SeedInput>,
+    mut ev_characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut q_text: Query<&mut Text, With<MainMenu>>,
+) {
+    for ev in ev_characters.read() {
+        if ev.char.is_ascii_digit() && input.0.len() < MAX_SEED_DIGITS {
+            input.0.push(ev.char);
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        input.0.pop();
+    }
+    for mut text in q_text.iter_mut() {
+        text.sections[2].value = input.0.clone();
+    }
+}
+
 pub fn handle_main_menu(
     mut state: ResMut<NextState<AppState>>,
+    mut mode: ResMut<GameMode>,
+    mut run_seed: ResMut<RunSeed>,
+    input: Res<SeedInput>,
     keyboard_input: Res<Input<KeyCode>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        state.set(AppState::InGame);
-    }
+    let chosen_mode = if keyboard_input.just_pressed(KeyCode::Space) {
+        GameMode::Survival
+    } else if keyboard_input.just_pressed(KeyCode::R) {
+        GameMode::BreakTheRifts
+    } else {
+        return;
+    };
+    *mode = chosen_mode;
+    *run_seed = input.0.parse().map_or_else(|_| RunSeed::random(), RunSeed);
+    state.set(AppState::InGame);
 }
 
 pub fn hide_main_menu(mut commands: Commands, mut q: Query<Entity, With<MainMenu>>) {
@@ -1,11 +1,13 @@
 mod game_over;
 mod main_menu;
+mod victory;
 
 use crate::resources::AppState;
 use crate::scenes::main_menu::*;
 use bevy::prelude::*;
 use crate::events::PlayerDies;
 use crate::scenes::game_over::{cleanup_game_over_ui, game_over_when_player_dies, handle_game_over, setup_game_over_ui};
+use crate::scenes::victory::{cleanup_victory_ui, handle_victory, setup_victory_ui};
 
 #[derive(Component)]
 pub struct ScenesPlugin;
@@ -15,11 +17,14 @@ impl Plugin for ScenesPlugin {
         app.add_state::<AppState>()
             .add_systems(Startup, setup_main_menu)
             .add_event::<PlayerDies>()
+            .init_resource::<SeedInput>()
             //mainmenu
             .add_systems(OnEnter(AppState::MainMenu), setup_main_menu)
             .add_systems(
                 Update,
-                handle_main_menu.run_if(in_state(AppState::MainMenu)),
+                (enter_seed, handle_main_menu)
+                    .chain()
+                    .run_if(in_state(AppState::MainMenu)),
             )
             .add_systems(OnExit(AppState::MainMenu), hide_main_menu)
             //ingame
@@ -28,6 +33,10 @@ impl Plugin for ScenesPlugin {
             .add_systems(OnEnter(AppState::GameOver), setup_game_over_ui)
             .add_systems(Update, handle_game_over.run_if(in_state(AppState::GameOver)))
             .add_systems(OnExit(AppState::GameOver), cleanup_game_over_ui)
+            //victory
+            .add_systems(OnEnter(AppState::Victory), setup_victory_ui)
+            .add_systems(Update, handle_victory.run_if(in_state(AppState::Victory)))
+            .add_systems(OnExit(AppState::Victory), cleanup_victory_ui)
         ;
     }
 }
@@ -0,0 +1,66 @@
+use crate::director::WaveDirector;
+use crate::objective::Objective;
+use crate::resources::AppState;
+use crate::seed::RunSeed;
+use crate::ui::Score;
+use bevy::prelude::*;
+
+#[derive(Component)]
+pub struct Victory;
+
+pub fn setup_victory_ui(
+    mut commands: Commands,
+    score: Res<Score>,
+    objective: Res<Objective>,
+    director: Res<WaveDirector>,
+    run_seed: Res<RunSeed>,
+) {
+    let seconds = director.elapsed.as_secs();
+    let results = format!(
+        "Rifts closed: {}\nScore: {}\nTime: {}:{:02}\nSeed: {}",
+        objective.rifts_closed,
+        score.0,
+        seconds / 60,
+        seconds % 60,
+        run_seed.0
+    );
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Victory | Press <space> to continue\n",
+                TextStyle {
+                    font_size: 40.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ),
+            TextSection::new(
+                results,
+                TextStyle {
+                    font_size: 30.,
+                    color: Color::DARK_GREEN,
+                    ..default()
+                },
+            ),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+        Victory,
+    ));
+}
+
+pub fn handle_victory(mut state: ResMut<NextState<AppState>>, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        state.set(AppState::MainMenu);
+    }
+}
+
+pub fn cleanup_victory_ui(mut commands: Commands, mut q: Query<Entity, With<Victory>>) {
+    for entity in q.iter_mut() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::resources::AppState::InGame;
+
+// rolled seeds stay short enough to read out and type back in
+const MAX_RANDOM_SEED: u64 = 1_000_000_000;
+// separate streams, so extra randomness in one system can't shift another
+const RIFT_STREAM: u64 = 0x5249_4654;
+const DROP_STREAM: u64 = 0x4452_4f50;
+const SPAWN_STREAM: u64 = 0x5350_574e;
+const ELITE_STREAM: u64 = 0x454c_4954;
+
+pub struct SeedPlugin;
+
+impl Plugin for SeedPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RunSeed::random())
+            .add_systems(OnEnter(InGame), seed_run_rngs);
+    }
+}
+
+// everything that should replay the same for a given seed draws from it
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RunSeed(pub u64);
+
+impl RunSeed {
+    pub fn random() -> Self {
+        Self(thread_rng().gen_range(0..MAX_RANDOM_SEED))
+    }
+
+    pub fn terrain_seed(self) -> u32 {
+        (self.0 ^ (self.0 >> 32)) as u32
+    }
+
+    fn rng(self, stream: u64) -> StdRng {
+        StdRng::seed_from_u64(self.0 ^ stream)
+    }
+}
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct RiftRng(pub StdRng);
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct DropRng(pub StdRng);
+
+// how many draws spawning makes depends on frame timing and the enemy cap
+#[derive(Resource, Deref, DerefMut)]
+pub struct SpawnRng(pub StdRng);
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct EliteRng(pub StdRng);
+
+fn seed_run_rngs(mut commands: Commands, seed: Res<RunSeed>) {
+    info!("starting run with seed {}", seed.0);
+    commands.insert_resource(RiftRng(seed.rng(RIFT_STREAM)));
+    commands.insert_resource(DropRng(seed.rng(DROP_STREAM)));
+    commands.insert_resource(SpawnRng(seed.rng(SPAWN_STREAM)));
+    commands.insert_resource(EliteRng(seed.rng(ELITE_STREAM)));
+}
@@ -0,0 +1,79 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::components::Velocity;
+use crate::enemy::Enemy;
+use crate::resources::AppState::InGame;
+
+const GRID_CELL_SIZE: f32 = 64.0;
+
+pub struct SpatialGridPlugin;
+
+impl Plugin for SpatialGridPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SpatialGrid::new(GRID_CELL_SIZE))
+            .add_systems(Update, update_enemy_grid.run_if(in_state(InGame)));
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct GridEntry {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+#[derive(Resource)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<GridEntry>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // keep the buffers of cells that were occupied last time around
+        self.cells.retain(|_, entries| !entries.is_empty());
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+
+    pub fn insert(&mut self, entry: GridEntry) {
+        let cell = self.cell_of(entry.position);
+        self.cells.entry(cell).or_default().push(entry);
+    }
+
+    pub fn cell_of(&self, position: Vec2) -> IVec2 {
+        (position / self.cell_size).floor().as_ivec2()
+    }
+
+    pub fn within(&self, position: Vec2, radius: f32) -> impl Iterator<Item = &GridEntry> {
+        let min = self.cell_of(position - Vec2::splat(radius));
+        let max = self.cell_of(position + Vec2::splat(radius));
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |entry| entry.position.distance_squared(position) <= radius * radius)
+    }
+}
+
+pub fn update_enemy_grid(
+    mut grid: ResMut<SpatialGrid>,
+    q_enemy: Query<(Entity, &Transform, &Velocity), With<Enemy>>,
+) {
+    grid.clear();
+    for (entity, transform, velocity) in q_enemy.iter() {
+        grid.insert(GridEntry {
+            entity,
+            position: transform.translation.truncate(),
+            velocity: velocity.0,
+        });
+    }
+}
@@ -0,0 +1,324 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::arena::{Arena, ArenaSource};
+use crate::boss::{spawn_boss, BOSS_BOUNDS_MARGIN};
+use crate::bounds::{ArenaBounds, BoundsShape};
+use crate::data::{asset_path, load_ron};
+use crate::director::WaveDirector;
+use crate::elite::ForceElite;
+use crate::enemy::{prepare_enemy, EnemyKind};
+use crate::resources::AppState::InGame;
+use crate::rift::{spawn_rift_at, RiftKind, RiftPlacement, RIFT_BOUNDS_MARGIN};
+
+const STAGE_PATH: &str = "stages/default.ron";
+const DEFAULT_RIFT_DISTANCE: f32 = 500.0;
+const DEFAULT_BOSS_DISTANCE: f32 = 600.0;
+
+pub struct StagePlugin;
+
+impl Plugin for StagePlugin {
+    fn build(&self, app: &mut App) {
+        let stage = Stage::load(STAGE_PATH);
+        // without an arena the stage plays on the endless procedural map
+        let arena = stage.arena.as_ref().map(|source| {
+            Arena::load(source).unwrap_or_else(|err| panic!("invalid stage in {STAGE_PATH}: {err}"))
+        });
+        let bounds = match (stage.bounds, &arena) {
+            (Some(shape), _) => Some(ArenaBounds {
+                shape,
+                center: Vec2::ZERO,
+            }),
+            (None, Some(arena)) => Some(arena.bounds()),
+            (None, None) => None,
+        };
+        if let Some(arena) = arena {
+            app.insert_resource(arena);
+        }
+        if let Some(bounds) = bounds {
+            app.insert_resource(bounds);
+        }
+        app.insert_resource(stage)
+            .add_systems(OnEnter(InGame), reset_timeline)
+            .add_systems(Update, run_timeline.run_if(in_state(InGame)));
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum StageEvent {
+    OpenRift {
+        kind: RiftKind,
+        #[serde(default = "default_rift_distance")]
+        distance: f32,
+    },
+    EnemyRing {
+        kind: EnemyKind,
+        count: u32,
+        radius: f32,
+        #[serde(default)]
+        elite: bool,
+    },
+    SpawnBoss {
+        #[serde(default = "default_boss_distance")]
+        distance: f32,
+    },
+    PlayMusic(String),
+}
+
+fn default_rift_distance() -> f32 {
+    DEFAULT_RIFT_DISTANCE
+}
+
+fn default_boss_distance() -> f32 {
+    DEFAULT_BOSS_DISTANCE
+}
+
+#[derive(Deserialize, Debug)]
+struct TimelineEntry {
+    // "mm:ss" since the start of the run
+    at: String,
+    event: StageEvent,
+}
+
+#[derive(Deserialize, Debug)]
+struct StageFile {
+    name: String,
+    #[serde(default = "default_random_rifts")]
+    random_rifts: bool,
+    #[serde(default)]
+    arena: Option<ArenaSource>,
+    // walls the play area in, centered on where the run starts
+    #[serde(default)]
+    bounds: Option<BoundsShape>,
+    timeline: Vec<TimelineEntry>,
+}
+
+fn default_random_rifts() -> bool {
+    true
+}
+
+#[derive(Resource)]
+pub struct Stage {
+    pub name: String,
+    // authored stages can switch off the director's random rifts
+    pub random_rifts: bool,
+    pub arena: Option<ArenaSource>,
+    pub bounds: Option<BoundsShape>,
+    timeline: Vec<(Duration, StageEvent)>,
+}
+
+#[derive(Component)]
+struct StageMusic;
+
+#[derive(Resource, Default)]
+struct TimelineProgress {
+    next_event: usize,
+}
+
+fn parse_timestamp(at: &str) -> Result<Duration, String> {
+    let (minutes, seconds) = at
+        .split_once(':')
+        .ok_or_else(|| format!("`{at}` is not a mm:ss timestamp"))?;
+    let minutes: u64 = minutes
+        .parse()
+        .map_err(|_| format!("`{at}` has invalid minutes"))?;
+    let seconds: u64 = seconds
+        .parse()
+        .map_err(|_| format!("`{at}` has invalid seconds"))?;
+    if seconds >= 60 {
+        return Err(format!("`{at}` has more than 59 seconds"));
+    }
+    Ok(Duration::from_secs(minutes * 60 + seconds))
+}
+
+impl StageEvent {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            StageEvent::OpenRift { distance, .. } | StageEvent::SpawnBoss { distance }
+                if *distance < 0. =>
+            {
+                Err(format!("distance must not be negative, got {distance}"))
+            }
+            StageEvent::EnemyRing { count: 0, .. } => Err("enemy ring has no enemies".to_string()),
+            StageEvent::EnemyRing { radius, .. } if *radius <= 0. => {
+                Err(format!("enemy ring radius must be positive, got {radius}"))
+            }
+            StageEvent::PlayMusic(path) if !asset_path(path).exists() => {
+                Err(format!("music `{path}` does not exist"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Stage {
+    fn load(path: &str) -> Self {
+        let file: StageFile = load_ron(path).unwrap_or_else(|err| panic!("{err}"));
+        Self::from_file(file).unwrap_or_else(|err| panic!("invalid stage in {path}: {err}"))
+    }
+
+    fn from_file(file: StageFile) -> Result<Self, String> {
+        let mut timeline = Vec::with_capacity(file.timeline.len());
+        for (index, entry) in file.timeline.into_iter().enumerate() {
+            let at = parse_timestamp(&entry.at)
+                .map_err(|err| format!("timeline entry {index}: {err}"))?;
+            entry
+                .event
+                .validate()
+                .map_err(|err| format!("timeline entry {index} at {}: {err}", entry.at))?;
+            timeline.push((at, entry.event));
+        }
+        // entries may be written in any order, the runner walks them by time
+        timeline.sort_by_key(|(at, _)| *at);
+        if let Some(bounds) = &file.bounds {
+            bounds.validate()?;
+        }
+        Ok(Self {
+            name: file.name,
+            random_rifts: file.random_rifts,
+            arena: file.arena,
+            bounds: file.bounds,
+            timeline,
+        })
+    }
+}
+
+fn reset_timeline(mut commands: Commands, stage: Res<Stage>) {
+    info!("starting stage `{}`", stage.name);
+    commands.insert_resource(TimelineProgress::default());
+}
+
+fn run_timeline(
+    mut commands: Commands,
+    stage: Res<Stage>,
+    mut progress: ResMut<TimelineProgress>,
+    director: Res<WaveDirector>,
+    asset_server: Res<AssetServer>,
+    q_music: Query<Entity, With<StageMusic>>,
+    mut placement: RiftPlacement,
+) {
+    let player_position = placement.player_position();
+    while let Some((at, event)) = stage.timeline.get(progress.next_event) {
+        if *at > director.elapsed {
+            break;
+        }
+        progress.next_event += 1;
+
+        match event {
+            StageEvent::OpenRift { kind, distance } => {
+                let location = placement.around_player(*distance, RIFT_BOUNDS_MARGIN);
+                spawn_rift_at(&mut commands, *kind, location, &director.difficulty());
+            }
+            StageEvent::EnemyRing {
+                kind,
+                count,
+                radius,
+                elite,
+            } => {
+                for i in 0..*count {
+                    let angle = TAU * i as f32 / *count as f32;
+                    let location = player_position + Vec2::from_angle(angle) * *radius;
+                    let mut enemy = commands.spawn(prepare_enemy(*kind, &location));
+                    if *elite {
+                        enemy.insert(ForceElite);
+                    }
+                }
+            }
+            StageEvent::SpawnBoss { distance } => {
+                let location = placement.around_player(*distance, BOSS_BOUNDS_MARGIN);
+                spawn_boss(&mut commands, location);
+            }
+            StageEvent::PlayMusic(path) => {
+                for entity in q_music.iter() {
+                    commands.entity(entity).despawn();
+                }
+                commands.spawn((
+                    AudioBundle {
+                        source: asset_server.load(path.clone()),
+                        settings: PlaybackSettings::LOOP,
+                    },
+                    StageMusic,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(timeline: &str) -> Result<Stage, String> {
+        let file: StageFile =
+            ron::from_str(&format!("(name: \"test\", timeline: [{timeline}])")).unwrap();
+        Stage::from_file(file)
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("00:00"), Ok(Duration::ZERO));
+        assert_eq!(parse_timestamp("02:30"), Ok(Duration::from_secs(150)));
+        assert_eq!(parse_timestamp("12:05"), Ok(Duration::from_secs(725)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for at in [
+            "", "90", "1:2:3", "ab:10", "01:xy", "-1:00", "01:60", "00:99",
+        ] {
+            assert!(parse_timestamp(at).is_err(), "`{at}` parsed");
+        }
+        let err = parse_timestamp("01:60").unwrap_err();
+        assert!(err.contains("59 seconds"), "{err}");
+    }
+
+    #[test]
+    fn rejects_invalid_events() {
+        let err = stage(r#"(at: "00:10", event: EnemyRing(kind: Grunt, count: 0, radius: 300.0))"#)
+            .err()
+            .unwrap();
+        assert!(
+            err.contains("entry 0") && err.contains("no enemies"),
+            "{err}"
+        );
+
+        let err = stage(
+            r#"(at: "00:10", event: SpawnBoss()),
+            (at: "00:20", event: OpenRift(kind: Swarm, distance: -5.0))"#,
+        )
+        .err()
+        .unwrap();
+        assert!(err.contains("entry 1") && err.contains("negative"), "{err}");
+
+        let err = stage(r#"(at: "0:75", event: SpawnBoss())"#).err().unwrap();
+        assert!(err.contains("entry 0"), "{err}");
+    }
+
+    #[test]
+    fn sorts_entries_by_time() {
+        let stage = stage(
+            r#"(at: "03:00", event: SpawnBoss()),
+            (at: "00:30", event: OpenRift(kind: Swarm)),
+            (at: "01:15", event: EnemyRing(kind: Grunt, count: 4, radius: 300.0))"#,
+        )
+        .unwrap();
+
+        let times: Vec<u64> = stage.timeline.iter().map(|(at, _)| at.as_secs()).collect();
+        assert_eq!(times, vec![30, 75, 180]);
+        assert!(
+            matches!(stage.timeline[0].1, StageEvent::OpenRift { distance, .. } if distance == DEFAULT_RIFT_DISTANCE)
+        );
+        assert!(matches!(stage.timeline[2].1, StageEvent::SpawnBoss { .. }));
+    }
+
+    #[test]
+    fn default_stage_is_valid() {
+        let file: StageFile = load_ron(STAGE_PATH).unwrap();
+        let stage = Stage::from_file(file).unwrap();
+        assert!(!stage.timeline.is_empty());
+        assert!(stage.arena.is_none());
+    }
+}
@@ -1,6 +1,14 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{prelude::*, sprite::collide_aabb::collide, window::PrimaryWindow};
 
-use crate::components::{Knockback, MainCamera, MouseWorldCoords, Velocity};
+use crate::bounds::ArenaBounds;
+use crate::components::{
+    BlockedByObstacles, Collider, Health, Knockback, MainCamera, MouseWorldCoords, Shootable,
+    Velocity,
+};
+use crate::elite::Armored;
+use crate::enemy::EnemyProjectile;
+use crate::map::TileOccupancy;
+use crate::player::Bullet;
 
 pub fn cursor_world_position(
     mut coords: ResMut<MouseWorldCoords>,
@@ -19,10 +27,70 @@ pub fn cursor_world_position(
     }
 }
 
-pub fn move_all(mut q_movable: Query<(&mut Transform, &Velocity)>, time: Res<Time<Fixed>>) {
-    for (mut transform, velocity) in q_movable.iter_mut() {
-        transform.translation.x += velocity.x * time.delta().as_secs_f32();
-        transform.translation.y += velocity.y * time.delta().as_secs_f32();
+pub fn move_all(
+    mut commands: Commands,
+    mut q_movable: Query<(
+        Entity,
+        &mut Transform,
+        &Velocity,
+        Option<&Collider>,
+        Has<BlockedByObstacles>,
+        Has<Knockback>,
+    )>,
+    occupancy: Res<TileOccupancy>,
+    bounds: Option<Res<ArenaBounds>>,
+    time: Res<Time<Fixed>>,
+) {
+    for (entity, mut transform, velocity, collider, blocked_by_obstacles, knocked_back) in
+        q_movable.iter_mut()
+    {
+        let delta = velocity.0 * time.delta().as_secs_f32();
+        let (Some(collider), true) = (collider, blocked_by_obstacles) else {
+            transform.translation.x += delta.x;
+            transform.translation.y += delta.y;
+            continue;
+        };
+
+        // one axis at a time, so running into a wall at an angle slides along it
+        let mut position = transform.translation.truncate();
+        let mut blocked = false;
+        for step in [Vec2::new(delta.x, 0.), Vec2::new(0., delta.y)] {
+            let stuck = occupancy.overlaps(position, collider.0);
+            // anything already inside an obstacle may still walk out of it
+            if stuck || !occupancy.overlaps(position + step, collider.0) {
+                position += step;
+            } else {
+                blocked = true;
+            }
+        }
+        if let Some(bounds) = &bounds {
+            let clamped = bounds.clamp(position, collider.0);
+            blocked |= clamped != position;
+            position = clamped;
+        }
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        // knocked into an obstacle or a wall, it would never cover the full knockback distance
+        if blocked && knocked_back {
+            commands.entity(entity).remove::<Knockback>();
+        }
+    }
+}
+
+pub fn obstacles_stop_projectiles(
+    mut commands: Commands,
+    q_projectile: Query<(Entity, &Transform), Or<(With<Bullet>, With<EnemyProjectile>)>>,
+    occupancy: Res<TileOccupancy>,
+    bounds: Option<Res<ArenaBounds>>,
+) {
+    for (entity, transform) in q_projectile.iter() {
+        let position = transform.translation.truncate();
+        let outside = bounds
+            .as_ref()
+            .is_some_and(|bounds| !bounds.contains(position, 0.));
+        if outside || occupancy.is_solid_at(position) {
+            commands.entity(entity).despawn();
+        }
     }
 }
 
@@ -39,3 +107,45 @@ pub fn handle_knockback(
         }
     }
 }
+
+pub fn bullet_hit_shootable(
+    mut commands: Commands,
+    q_bullet: Query<(&Transform, Entity, &Collider, &Velocity), With<Bullet>>,
+    mut q_target: Query<
+        (
+            &Transform,
+            &Collider,
+            &mut Health,
+            Entity,
+            Option<&Armored>,
+            Has<Velocity>,
+        ),
+        With<Shootable>,
+    >,
+) {
+    for (bullet_transform, bullet_entity, bullet_collider, velocity) in q_bullet.iter() {
+        for (target_transform, target_collider, mut health, entity, armored, movable) in
+            q_target.iter_mut()
+        {
+            if let Some(_) = collide(
+                bullet_transform.translation,
+                bullet_collider.0,
+                target_transform.translation,
+                target_collider.0,
+            ) {
+                commands.entity(bullet_entity).despawn();
+                let damage_reduction = armored.map_or(0., |armored| armored.damage_reduction);
+                health.current -= 1. - damage_reduction;
+                // rifts and other static targets don't get pushed around
+                if movable {
+                    let knockback = Knockback {
+                        velocity: velocity.normalize() * 20.,
+                        start_position: target_transform.translation.truncate(),
+                        distance: 10.,
+                    };
+                    commands.entity(entity).insert(knockback);
+                }
+            }
+        }
+    }
+}
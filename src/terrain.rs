@@ -0,0 +1,334 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+// noise is sampled in tile coordinates, larger scales mean larger patches
+const ELEVATION_SCALE: f32 = 24.0;
+const MOISTURE_SCALE: f32 = 40.0;
+const OCTAVES: u32 = 4;
+
+const WATER_LEVEL: f32 = 0.32;
+const STONE_LEVEL: f32 = 0.68;
+const DIRT_MOISTURE: f32 = 0.42;
+// how much darker the most shaded tiles get
+const SHADE_VARIATION: f32 = 0.12;
+
+const ROCK_CHANCE: u32 = 18;
+const TREE_CHANCE: u32 = 4;
+// wall segments are laid out on a coarse grid, one candidate per square
+const WALL_SPACING: i32 = 24;
+const WALL_LENGTH: i32 = 7;
+const WALL_CHANCE: u32 = 35;
+// chance per thousand tiles
+const PROP_CHANCE: u32 = 6;
+const HAZARD_CHANCE: u32 = 2;
+// hazard patches are rectangles of this many tiles a side
+const HAZARD_MIN_SIZE: u32 = 3;
+const HAZARD_MAX_SIZE: u32 = 6;
+// no obstacles this close to the origin, where every run starts
+const SPAWN_CLEARING: i32 = 4;
+
+// at most one landmark per square of this many tiles
+const LANDMARK_SPACING: i32 = 10;
+const LANDMARK_CHANCE: u32 = 45;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Terrain {
+    Grass,
+    Dirt,
+    Stone,
+    Water,
+}
+
+impl Terrain {
+    // position of the tile in the terrain atlas
+    pub fn texture_index(self) -> u32 {
+        match self {
+            Terrain::Grass => 0,
+            Terrain::Dirt => 1,
+            Terrain::Stone => 2,
+            Terrain::Water => 3,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Landmark {
+    Boulder,
+    Pillar,
+    Ruin,
+}
+
+impl Landmark {
+    pub fn size(self) -> Vec2 {
+        match self {
+            Landmark::Boulder => Vec2::new(48.0, 40.0),
+            Landmark::Pillar => Vec2::new(28.0, 96.0),
+            Landmark::Ruin => Vec2::new(96.0, 64.0),
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Landmark::Boulder => Color::rgb(0.45, 0.45, 0.45),
+            Landmark::Pillar => Color::rgb(0.3, 0.28, 0.35),
+            Landmark::Ruin => Color::rgb(0.55, 0.45, 0.35),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Obstacle {
+    Rock,
+    Tree,
+    Wall,
+}
+
+impl Obstacle {
+    pub fn size(self) -> Vec2 {
+        match self {
+            Obstacle::Rock => Vec2::new(30.0, 26.0),
+            Obstacle::Tree => Vec2::new(26.0, 32.0),
+            Obstacle::Wall => Vec2::new(32.0, 32.0),
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Obstacle::Rock => Color::rgb(0.35, 0.35, 0.38),
+            Obstacle::Tree => Color::rgb(0.1, 0.35, 0.12),
+            Obstacle::Wall => Color::rgb(0.4, 0.3, 0.25),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PropKind {
+    Crate,
+    Crystal,
+    Brazier,
+}
+
+pub struct PropStats {
+    pub size: Vec2,
+    pub color: Color,
+    pub health: f32,
+    pub xp: f32,
+}
+
+impl PropKind {
+    pub fn stats(self) -> PropStats {
+        match self {
+            PropKind::Crate => PropStats {
+                size: Vec2::new(24.0, 24.0),
+                color: Color::rgb(0.6, 0.4, 0.2),
+                health: 3.,
+                xp: 1.,
+            },
+            PropKind::Crystal => PropStats {
+                size: Vec2::new(18.0, 30.0),
+                color: Color::CYAN,
+                health: 6.,
+                xp: 3.,
+            },
+            PropKind::Brazier => PropStats {
+                size: Vec2::new(20.0, 20.0),
+                color: Color::ORANGE,
+                health: 2.,
+                xp: 1.,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum HazardKind {
+    Lava,
+    Mud,
+    Ice,
+    Spring,
+}
+
+impl HazardKind {
+    // how map files spell them
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lava" => Some(HazardKind::Lava),
+            "mud" => Some(HazardKind::Mud),
+            "ice" => Some(HazardKind::Ice),
+            "spring" => Some(HazardKind::Spring),
+            _ => None,
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            HazardKind::Lava => Color::rgba(1.0, 0.3, 0.0, 0.55),
+            HazardKind::Mud => Color::rgba(0.35, 0.22, 0.1, 0.6),
+            HazardKind::Ice => Color::rgba(0.75, 0.9, 1.0, 0.6),
+            HazardKind::Spring => Color::rgba(0.3, 1.0, 0.7, 0.45),
+        }
+    }
+}
+
+// integer hash of a lattice point, the same inputs always give the same value
+fn hash(x: i32, y: i32, seed: u32) -> u32 {
+    let mut h = seed ^ (x as u32).wrapping_mul(0x27d4_eb2d) ^ (y as u32).wrapping_mul(0x1656_67b1);
+    h = (h ^ (h >> 15)).wrapping_mul(0x85eb_ca6b);
+    h = (h ^ (h >> 13)).wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+fn lattice(x: i32, y: i32, seed: u32) -> f32 {
+    hash(x, y, seed) as f32 / u32::MAX as f32
+}
+
+fn value_noise(point: Vec2, seed: u32) -> f32 {
+    let cell = point.floor();
+    let (x, y) = (cell.x as i32, cell.y as i32);
+    let t = point - cell;
+    let t = t * t * (3.0 - 2.0 * t);
+
+    let bottom = lattice(x, y, seed) + (lattice(x + 1, y, seed) - lattice(x, y, seed)) * t.x;
+    let top =
+        lattice(x, y + 1, seed) + (lattice(x + 1, y + 1, seed) - lattice(x, y + 1, seed)) * t.x;
+    bottom + (top - bottom) * t.y
+}
+
+// layered value noise in 0..1
+fn fractal_noise(point: Vec2, seed: u32) -> f32 {
+    let mut total = 0.0;
+    let mut amplitude = 1.0;
+    let mut frequency = 1.0;
+    let mut max = 0.0;
+    for octave in 0..OCTAVES {
+        total += value_noise(point * frequency, seed.wrapping_add(octave)) * amplitude;
+        max += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    total / max
+}
+
+pub fn terrain_at(tile: IVec2, seed: u32) -> Terrain {
+    let point = tile.as_vec2();
+    let elevation = fractal_noise(point / ELEVATION_SCALE, seed);
+    let moisture = fractal_noise(point / MOISTURE_SCALE, seed.wrapping_add(0x9e37_79b9));
+    if elevation < WATER_LEVEL {
+        Terrain::Water
+    } else if elevation > STONE_LEVEL {
+        Terrain::Stone
+    } else if moisture < DIRT_MOISTURE {
+        Terrain::Dirt
+    } else {
+        Terrain::Grass
+    }
+}
+
+// a slightly darker or lighter shade per tile, so patches of one texture don't look stamped
+pub fn shade_at(tile: IVec2, seed: u32) -> Color {
+    let shade = 1.0 - SHADE_VARIATION * lattice(tile.x, tile.y, seed.wrapping_add(0x2545_f491));
+    Color::rgb(shade, shade, shade)
+}
+
+// every landmark square picks one tile as its anchor, most squares get a landmark there
+pub fn landmark_at(tile: IVec2, seed: u32) -> Option<Landmark> {
+    let square = tile.div_euclid(IVec2::splat(LANDMARK_SPACING));
+    let roll = hash(square.x, square.y, seed.wrapping_add(0x7f4a_7c15));
+    let anchor = square * LANDMARK_SPACING
+        + IVec2::new(
+            (roll % LANDMARK_SPACING as u32) as i32,
+            ((roll >> 8) % LANDMARK_SPACING as u32) as i32,
+        );
+    if tile != anchor || (roll >> 16) % 100 >= LANDMARK_CHANCE {
+        return None;
+    }
+    if terrain_at(tile, seed) == Terrain::Water {
+        return None;
+    }
+    Some(match (roll >> 24) % 3 {
+        0 => Landmark::Boulder,
+        1 => Landmark::Pillar,
+        _ => Landmark::Ruin,
+    })
+}
+
+fn on_wall(tile: IVec2, seed: u32) -> bool {
+    let square = tile.div_euclid(IVec2::splat(WALL_SPACING));
+    let roll = hash(square.x, square.y, seed.wrapping_add(0x2545_f491));
+    if roll % 100 >= WALL_CHANCE {
+        return false;
+    }
+    // keep the whole segment inside its square
+    let slack = (WALL_SPACING - WALL_LENGTH) as u32;
+    let start = square * WALL_SPACING
+        + IVec2::new(((roll >> 8) % slack) as i32, ((roll >> 16) % slack) as i32);
+    let along = if (roll >> 24) % 2 == 0 {
+        IVec2::X
+    } else {
+        IVec2::Y
+    };
+    let offset = tile - start;
+    let step = offset.dot(along);
+    offset == along * step && (0..WALL_LENGTH).contains(&step)
+}
+
+// solid tiles, the same tile always gets the same obstacle for a seed
+pub fn obstacle_at(tile: IVec2, seed: u32) -> Option<Obstacle> {
+    if tile.abs().max_element() <= SPAWN_CLEARING || landmark_at(tile, seed).is_some() {
+        return None;
+    }
+    let terrain = terrain_at(tile, seed);
+    if terrain == Terrain::Water {
+        return None;
+    }
+    if on_wall(tile, seed) {
+        return Some(Obstacle::Wall);
+    }
+    let roll = hash(tile.x, tile.y, seed.wrapping_add(0x6c07_8965)) % 100;
+    match terrain {
+        Terrain::Stone if roll < ROCK_CHANCE => Some(Obstacle::Rock),
+        Terrain::Grass if roll < TREE_CHANCE => Some(Obstacle::Tree),
+        _ => None,
+    }
+}
+
+// destructible props only go on open ground
+pub fn prop_at(tile: IVec2, seed: u32) -> Option<PropKind> {
+    let roll = hash(tile.x, tile.y, seed.wrapping_add(0x3c6e_f372));
+    if roll % 1000 >= PROP_CHANCE || tile.abs().max_element() <= SPAWN_CLEARING {
+        return None;
+    }
+    if obstacle_at(tile, seed).is_some() || landmark_at(tile, seed).is_some() {
+        return None;
+    }
+    match terrain_at(tile, seed) {
+        Terrain::Water => None,
+        Terrain::Stone => Some(PropKind::Crystal),
+        Terrain::Dirt => Some(PropKind::Crate),
+        Terrain::Grass if (roll >> 16) % 2 == 0 => Some(PropKind::Crate),
+        Terrain::Grass => Some(PropKind::Brazier),
+    }
+}
+
+// a patch grows up and to the right from its anchor tile, the size is in tiles
+pub fn hazard_at(tile: IVec2, seed: u32) -> Option<(HazardKind, IVec2)> {
+    let roll = hash(tile.x, tile.y, seed.wrapping_add(0x6a09_e667));
+    if roll % 1000 >= HAZARD_CHANCE
+        || tile.abs().max_element() <= SPAWN_CLEARING + HAZARD_MAX_SIZE as i32
+    {
+        return None;
+    }
+    let kind = match terrain_at(tile, seed) {
+        Terrain::Stone => HazardKind::Lava,
+        Terrain::Dirt => HazardKind::Mud,
+        Terrain::Water => HazardKind::Ice,
+        Terrain::Grass if (roll >> 16) % 3 == 0 => HazardKind::Spring,
+        Terrain::Grass => return None,
+    };
+    let sizes = HAZARD_MAX_SIZE - HAZARD_MIN_SIZE + 1;
+    let size = UVec2::new(
+        HAZARD_MIN_SIZE + (roll >> 20) % sizes,
+        HAZARD_MIN_SIZE + (roll >> 26) % sizes,
+    );
+    Some((kind, size.as_ivec2()))
+}
@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 
+use crate::boss::Boss;
+use crate::components::Health;
 use crate::events::PlayerHealthChanged;
-use crate::resources::{AppState, XP};
+use crate::objective::Objective;
+use crate::resources::{AppState, GameMode, XP};
 
 #[derive(Component)]
 pub struct UIPlugin;
@@ -10,12 +13,24 @@ impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             OnEnter(AppState::InGame),
-            (setup_player_health, setup_score_board, setup_xp_ui),
+            (
+                setup_player_health,
+                setup_score_board,
+                setup_xp_ui,
+                setup_boss_health_bar,
+                setup_objective_ui.run_if(resource_equals(GameMode::BreakTheRifts)),
+            ),
         )
         .add_event::<PlayerHealthChanged>()
         .add_systems(
             Update,
-            (update_player_health_ui, update_score_ui, update_xp_ui)
+            (
+                update_player_health_ui,
+                update_score_ui,
+                update_xp_ui,
+                update_boss_health_bar,
+                update_objective_ui.run_if(resource_exists::<Objective>()),
+            )
                 .run_if(in_state(AppState::InGame)),
         );
     }
@@ -33,6 +48,15 @@ struct ScoreUI;
 #[derive(Component)]
 struct XpUI;
 
+#[derive(Component)]
+struct BossHealthBar;
+
+#[derive(Component)]
+struct BossHealthBarFill;
+
+#[derive(Component)]
+struct ObjectiveUI;
+
 fn setup_player_health(mut commands: Commands) {
     commands.spawn((
         TextBundle::from_sections([
@@ -127,6 +151,67 @@ fn setup_xp_ui(mut commands: Commands) {
     ));
 }
 
+fn setup_boss_health_bar(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(60.0),
+                    left: Val::Percent(25.0),
+                    width: Val::Percent(50.0),
+                    height: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.2, 0.2, 0.2).into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            BossHealthBar,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::MAROON.into(),
+                    ..default()
+                },
+                BossHealthBarFill,
+            ));
+        });
+}
+
+fn setup_objective_ui(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_sections([
+            TextSection::new(
+                "Rifts closed ",
+                TextStyle {
+                    font_size: 30.,
+                    color: Color::BLACK,
+                    ..default()
+                },
+            ),
+            TextSection::from_style(TextStyle {
+                font_size: 30.,
+                color: Color::PURPLE,
+                ..default()
+            }),
+        ])
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            right: Val::Px(5.0),
+            ..default()
+        }),
+        ObjectiveUI,
+    ));
+}
+
 fn update_player_health_ui(
     mut ev_player_health: EventReader<PlayerHealthChanged>,
     mut q_text: Query<&mut Text, With<PlayerHealthUI>>,
@@ -150,3 +235,43 @@ fn update_xp_ui(mut q_text: Query<&mut Text, With<XpUI>>, xp: Res<XP>) {
         text.sections[1].value = xp.0.to_string();
     }
 }
+
+fn update_boss_health_bar(
+    q_boss: Query<&Health, With<Boss>>,
+    mut q_bar: Query<&mut Visibility, With<BossHealthBar>>,
+    mut q_fill: Query<&mut Style, With<BossHealthBarFill>>,
+) {
+    // with several bosses alive, track the one closest to dying
+    let boss_health = q_boss
+        .iter()
+        .map(|health| (health.current / health.max).max(0.))
+        .reduce(f32::min);
+
+    for mut visibility in q_bar.iter_mut() {
+        *visibility = match boss_health {
+            Some(_) => Visibility::Inherited,
+            None => Visibility::Hidden,
+        };
+    }
+    if let Some(fraction) = boss_health {
+        for mut style in q_fill.iter_mut() {
+            style.width = Val::Percent(fraction * 100.0);
+        }
+    }
+}
+
+fn update_objective_ui(objective: Res<Objective>, mut q_text: Query<&mut Text, With<ObjectiveUI>>) {
+    let progress = if !objective.rifts_done() {
+        format!(
+            "{} / {}",
+            objective.rifts_closed, objective.config.rifts_to_close
+        )
+    } else if objective.final_rift_closed {
+        "done".to_string()
+    } else {
+        "- close the final rift".to_string()
+    };
+    for mut text in q_text.iter_mut() {
+        text.sections[1].value = progress.clone();
+    }
+}